@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pbr::{MultiBar, ProgressBar};
+use std::io::sink;
+
+const N: u64 = 10_000;
+
+fn inc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inc");
+    group.sample_size(20);
+
+    group.bench_function("default", |b| {
+        b.iter(|| {
+            let mut pb = ProgressBar::on(sink(), N);
+            for _ in 0..N {
+                black_box(pb.inc());
+            }
+        })
+    });
+
+    group.bench_function("every update", |b| {
+        b.iter(|| {
+            let mut pb = ProgressBar::on(sink(), N);
+            pb.set_max_refresh_rate(None);
+            for _ in 0..N {
+                black_box(pb.inc());
+            }
+        })
+    });
+
+    group.bench_function("multibar", |b| {
+        b.iter(|| {
+            let mb = MultiBar::on(sink());
+            let mut pb = mb.create_bar(N);
+            for _ in 0..N {
+                black_box(pb.inc());
+            }
+            pb.finish();
+            mb.listen();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, inc);
+criterion_main!(benches);
@@ -12,7 +12,7 @@ fn main() {
         let count = 100 * i;
         let mut pb = mb.create_bar(count);
         pb.tick_format("▏▎▍▌▋▊▉██▉▊▋▌▍▎▏");
-        pb.show_message = true;
+        pb.set_show_tick(true);
         thread::spawn(move || {
             for _ in 0..count / 20 {
                 for _ in 0..20 {
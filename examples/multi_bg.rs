@@ -40,9 +40,9 @@ fn main() {
     });
 
     while !complete.load(Ordering::SeqCst) {
-        let _ = progress.listen();
+        progress.listen();
         thread::sleep(Duration::from_millis(1000));
     }
 
-    let _ = progress.listen();
+    progress.listen();
 }
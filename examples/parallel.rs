@@ -0,0 +1,20 @@
+use pbr::{ParallelProgressIterator, ProgressBar};
+use rayon::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let mut pb = ProgressBar::new(0);
+    pb.format("╢▌▌░╟");
+
+    let sum: u64 = (0..2000u64)
+        .into_par_iter()
+        .progress_with(pb)
+        .map(|i| {
+            thread::sleep(Duration::from_millis(5));
+            i * 2
+        })
+        .sum();
+
+    println!("\nsum: {}", sum);
+}
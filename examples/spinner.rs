@@ -0,0 +1,25 @@
+use pbr::{ProgressBar, Spinner, Style};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let spinners = [
+        ("dots", Spinner::dots()),
+        ("line", Spinner::line()),
+        ("arc", Spinner::arc()),
+        ("bouncing bar", Spinner::bouncing_bar()),
+        ("clock", Spinner::clock()),
+        ("moon", Spinner::moon()),
+    ];
+    for (name, spinner) in spinners.iter() {
+        let mut pb = ProgressBar::new(0);
+        pb.set_style(&Style::spinner_only().spinner(spinner))
+            .unwrap();
+        pb.message(&format!("{} ", name));
+        for _ in 0..100 {
+            pb.tick();
+            thread::sleep(Duration::from_millis(20));
+        }
+        pb.finish_println("");
+    }
+}
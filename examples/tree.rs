@@ -0,0 +1,49 @@
+use pbr::MultiBar;
+use rand::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let mb = MultiBar::new();
+    mb.println("Pipeline:");
+
+    let root = mb.create_tree(100);
+    root.message("pipeline ");
+    root.collapse_finished(true);
+
+    // stage 1 is 30% of the work, and has its own sub-steps.
+    let stage1 = root.create_child(0.3, 100);
+    stage1.message("download ");
+    let steps: Vec<_> = (1..4)
+        .map(|i| {
+            let step = stage1.create_child(1.0, 50 * i);
+            step.message(&format!("part {} ", i));
+            step
+        })
+        .collect();
+
+    let stage2 = root.create_child(0.7, 200);
+    stage2.message("process ");
+
+    thread::spawn(move || {
+        for step in steps {
+            for _ in 0..step.with_bar(|pb| pb.total()) {
+                step.inc();
+                let n = thread_rng().gen_range(0..20);
+                thread::sleep(Duration::from_millis(n));
+            }
+            step.finish();
+        }
+        stage1.finish();
+
+        for _ in 0..200 {
+            stage2.inc();
+            thread::sleep(Duration::from_millis(30));
+        }
+        root.finish();
+    });
+
+    mb.listen();
+
+    println!("\npipeline done!\n");
+}
@@ -0,0 +1,409 @@
+use crate::ProgressBar;
+use std::io::{self, Stdout, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// Tracker updates the bar of an adapter from its poll methods. The bar is
+// drawn at most at its max refresh rate, so most polls only add to the
+// counter and never write to the terminal.
+struct Tracker<T: Write> {
+    pb: ProgressBar<T>,
+}
+
+impl<T: Write> Tracker<T> {
+    fn add(&mut self, n: u64) {
+        self.pb.add(n);
+    }
+
+    fn complete(&mut self) {
+        if !self.pb.is_finish() {
+            self.pb.finish();
+        }
+    }
+}
+
+impl<T: Write> Drop for Tracker<T> {
+    // the adapter was dropped before the stream ended.
+    fn drop(&mut self) {
+        if self.pb.is_finish() {
+            return;
+        }
+        if self.pb.current < self.pb.total {
+            self.pb.abandon();
+        } else {
+            self.pb.finish();
+        }
+    }
+}
+
+/// ProgressStream wraps a `Stream` and increments a `ProgressBar` for every
+/// item, see `ProgressStreamExt`.
+///
+/// The bar is updated when the stream is polled, and is finished when the
+/// stream ends. A `ProgressStream` that is dropped before that abandons
+/// the bar, unless it reached its total.
+///
+/// It is a `Stream` only if the wrapped stream is `Unpin`. Pin other
+/// streams with `Box::pin` before wrapping them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::executor::block_on;
+/// use futures::stream::{self, StreamExt};
+/// use pbr::ProgressStreamExt;
+///
+/// // `unfold` holds a future, so it is not `Unpin`.
+/// let numbers = stream::unfold(0, |n| async move { (n < 10).then(|| (n, n + 1)) });
+/// let items: Vec<i32> = block_on(Box::pin(numbers).progress().collect());
+/// ```
+#[cfg(feature = "futures")]
+pub struct ProgressStream<S, T: Write = Stdout> {
+    inner: S,
+    tracker: Tracker<T>,
+}
+
+/// ProgressStreamExt adds progress bars to any `Stream`.
+///
+/// Available with the `futures` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::executor::block_on;
+/// use futures::stream::{self, StreamExt};
+/// use pbr::ProgressStreamExt;
+///
+/// let items: Vec<i32> = block_on(stream::iter(0..100).progress().collect());
+/// ```
+#[cfg(feature = "futures")]
+pub trait ProgressStreamExt: futures_core::Stream + Sized {
+    /// Wrap the stream with a new `ProgressBar` on stdout. The total is the
+    /// upper bound of the stream size hint, or its lower bound if there is none.
+    fn progress(self) -> ProgressStream<Self> {
+        self.progress_with(ProgressBar::new(0))
+    }
+
+    /// Wrap the stream with the given `ProgressBar`.
+    ///
+    /// If the total of the bar is `0`, it is set from the size hint of
+    /// the stream, like in `progress`.
+    fn progress_with<T: Write>(self, mut pb: ProgressBar<T>) -> ProgressStream<Self, T> {
+        if pb.total == 0 {
+            let (lower, upper) = self.size_hint();
+            pb.total = upper.unwrap_or(lower) as u64;
+        }
+        ProgressStream {
+            inner: self,
+            tracker: Tracker { pb },
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S: futures_core::Stream> ProgressStreamExt for S {}
+
+#[cfg(feature = "futures")]
+impl<S, T> futures_core::Stream for ProgressStream<S, T>
+where
+    S: futures_core::Stream + Unpin,
+    T: Write + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let r = Pin::new(&mut self.inner).poll_next(cx);
+        match r {
+            Poll::Ready(Some(_)) => self.tracker.add(1),
+            Poll::Ready(None) => self.tracker.complete(),
+            Poll::Pending => {}
+        }
+        r
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// ProgressAsyncRead wraps an async reader and adds the bytes read from it
+/// to a `ProgressBar`. It implements `futures::io::AsyncRead` with the
+/// `futures` feature, and `tokio::io::AsyncRead` with the `tokio` feature.
+///
+/// The bar is updated when the reader is polled, and is finished when the
+/// reader reaches its end. A `ProgressAsyncRead` that is dropped before
+/// that abandons the bar, unless it reached its total.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "futures")]
+/// # {
+/// use futures::executor::block_on;
+/// use futures::io::{copy, sink, Cursor};
+/// use pbr::{ProgressAsyncRead, ProgressBar};
+///
+/// let data = vec![0u8; 1 << 20];
+/// let pb = ProgressBar::new(data.len() as u64);
+/// let mut reader = ProgressAsyncRead::new(Cursor::new(data), pb);
+/// block_on(copy(&mut reader, &mut sink())).unwrap();
+/// # }
+/// ```
+pub struct ProgressAsyncRead<R, T: Write = Stdout> {
+    inner: R,
+    tracker: Tracker<T>,
+}
+
+impl<R, T: Write> ProgressAsyncRead<R, T> {
+    /// Wrap `reader`, reading from it updates `pb`.
+    pub fn new(reader: R, pb: ProgressBar<T>) -> ProgressAsyncRead<R, T> {
+        ProgressAsyncRead {
+            inner: reader,
+            tracker: Tracker { pb },
+        }
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<R, T> futures_io::AsyncRead for ProgressAsyncRead<R, T>
+where
+    R: futures_io::AsyncRead + Unpin,
+    T: Write + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let r = Pin::new(&mut self.inner).poll_read(cx, buf);
+        match r {
+            Poll::Ready(Ok(0)) if !buf.is_empty() => self.tracker.complete(),
+            Poll::Ready(Ok(n)) => self.tracker.add(n as u64),
+            _ => {}
+        }
+        r
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, T> tokio::io::AsyncRead for ProgressAsyncRead<R, T>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    T: Write + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let r = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = r {
+            let n = buf.filled().len() - before;
+            if n == 0 && buf.remaining() > 0 {
+                self.tracker.complete();
+            } else {
+                self.tracker.add(n as u64);
+            }
+        }
+        r
+    }
+}
+
+/// ProgressAsyncWrite wraps an async writer and adds the bytes written to
+/// it to a `ProgressBar`. It implements `futures::io::AsyncWrite` with the
+/// `futures` feature, and `tokio::io::AsyncWrite` with the `tokio` feature.
+///
+/// Only the bytes accepted by the underlying writer are counted. The bar is
+/// updated when the writer is polled, and is finished when the writer is
+/// closed (or shut down). A `ProgressAsyncWrite` that is dropped before that
+/// abandons the bar, unless it reached its total.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "futures")]
+/// # {
+/// use futures::executor::block_on;
+/// use futures::io::{copy, AsyncWriteExt, Cursor};
+/// use pbr::{ProgressAsyncWrite, ProgressBar};
+///
+/// let data = vec![0u8; 1 << 20];
+/// let pb = ProgressBar::new(data.len() as u64);
+/// let mut writer = ProgressAsyncWrite::new(Vec::new(), pb);
+/// block_on(async {
+///     copy(&mut Cursor::new(data), &mut writer).await.unwrap();
+///     writer.close().await.unwrap();
+/// });
+/// # }
+/// ```
+pub struct ProgressAsyncWrite<W, T: Write = Stdout> {
+    inner: W,
+    tracker: Tracker<T>,
+}
+
+impl<W, T: Write> ProgressAsyncWrite<W, T> {
+    /// Wrap `writer`, writing to it updates `pb`.
+    pub fn new(writer: W, pb: ProgressBar<T>) -> ProgressAsyncWrite<W, T> {
+        ProgressAsyncWrite {
+            inner: writer,
+            tracker: Tracker { pb },
+        }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn written(&mut self, r: &Poll<io::Result<usize>>) {
+        if let Poll::Ready(Ok(n)) = r {
+            self.tracker.add(*n as u64);
+        }
+    }
+
+    fn closed(&mut self, r: &Poll<io::Result<()>>) {
+        if let Poll::Ready(Ok(())) = r {
+            self.tracker.complete();
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<W, T> futures_io::AsyncWrite for ProgressAsyncWrite<W, T>
+where
+    W: futures_io::AsyncWrite + Unpin,
+    T: Write + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let r = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.written(&r);
+        r
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let r = Pin::new(&mut self.inner).poll_close(cx);
+        self.closed(&r);
+        r
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W, T> tokio::io::AsyncWrite for ProgressAsyncWrite<W, T>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    T: Write + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let r = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.written(&r);
+        r
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let r = Pin::new(&mut self.inner).poll_shutdown(cx);
+        self.closed(&r);
+        r
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::fixture::Shared;
+    use crate::ProgressBar;
+
+    // the last frame drawn on `out`.
+    fn last(out: &Shared) -> String {
+        out.text().rsplit('\r').next().unwrap().to_owned()
+    }
+
+    fn bar(out: &Shared, total: u64) -> ProgressBar<Shared> {
+        let mut pb = ProgressBar::with_config(out.clone(), total, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_color(false);
+        pb.set_width(Some(40));
+        pb
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream() {
+        use crate::ProgressStreamExt;
+        use futures::executor::block_on;
+        use futures::stream::{self, StreamExt};
+
+        let out = Shared::default();
+        let s = stream::iter(0..10).progress_with(bar(&out, 0));
+        let v: Vec<i32> = block_on(s.collect());
+        assert_eq!(v.len(), 10);
+        assert!(last(&out).starts_with("10 / 10 "), "{:?}", last(&out));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn futures_read_write() {
+        use crate::{ProgressAsyncRead, ProgressAsyncWrite};
+        use futures::executor::block_on;
+        use futures::io::{copy, AsyncWriteExt, Cursor};
+
+        let (rout, wout) = (Shared::default(), Shared::default());
+        let mut r = ProgressAsyncRead::new(Cursor::new(vec![1u8; 100]), bar(&rout, 100));
+        let mut w = ProgressAsyncWrite::new(Vec::new(), bar(&wout, 200));
+        block_on(async {
+            copy(&mut r, &mut w).await.unwrap();
+            w.close().await.unwrap();
+        });
+        assert_eq!(w.into_inner().len(), 100);
+        assert!(last(&rout).starts_with("100 / 100 "), "{:?}", last(&rout));
+        assert!(last(&wout).starts_with("200 / 200 "), "{:?}", last(&wout));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_read_dropped() {
+        use crate::ProgressAsyncRead;
+        use futures::executor::block_on;
+        use tokio::io::AsyncReadExt;
+
+        let out = Shared::default();
+        let data = [1u8; 100];
+        let mut r = ProgressAsyncRead::new(&data[..], bar(&out, 100));
+        let mut buf = [0u8; 30];
+        block_on(r.read_exact(&mut buf)).unwrap();
+        drop(r);
+        assert!(last(&out).starts_with("✖ 30 / 100 "), "{:?}", last(&out));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{ConfigError, ErrorPolicy, ProgressBar, Stream, Style};
+use std::io::{stdout, Stdout, Write};
+use std::time::Duration;
+
+/// ProgressBarBuilder configures a `ProgressBar`, and checks the
+/// configuration before the bar is created.
+///
+/// Options that are not set keep their defaults, including the ones that
+/// are set with environment variables.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::{ProgressBarBuilder, Style, Units};
+/// use std::time::Duration;
+///
+/// let style = Style::new().units(Units::Bytes).show_speed(false);
+/// let mut pb = ProgressBarBuilder::new(1024)
+///     .style(&style)
+///     .message("download ")
+///     .max_refresh_rate(Some(Duration::from_millis(100)))
+///     .build()
+///     .unwrap();
+/// pb.add(512);
+/// ```
+pub struct ProgressBarBuilder<T: Write> {
+    handle: T,
+    total: u64,
+    stream: Option<Stream>,
+    style: Style,
+    message: String,
+    width: Option<Option<usize>>,
+    max_refresh_rate: Option<Option<Duration>>,
+    error_policy: ErrorPolicy,
+    color: Option<bool>,
+    json: bool,
+    taskbar: bool,
+    title: Option<String>,
+    hide_cursor: bool,
+}
+
+impl ProgressBarBuilder<Stdout> {
+    /// Create a new ProgressBarBuilder with stdout as a writer.
+    pub fn new(total: u64) -> ProgressBarBuilder<Stdout> {
+        ProgressBarBuilder::on(stdout(), total).stream(Some(Stream::Stdout))
+    }
+}
+
+impl<T: Write> ProgressBarBuilder<T> {
+    /// Create a new ProgressBarBuilder with an arbitrary writer.
+    pub fn on(handle: T, total: u64) -> ProgressBarBuilder<T> {
+        ProgressBarBuilder {
+            handle,
+            total,
+            stream: None,
+            style: Style::new(),
+            message: String::new(),
+            width: None,
+            max_refresh_rate: None,
+            error_policy: ErrorPolicy::Disable,
+            color: None,
+            json: false,
+            taskbar: false,
+            title: None,
+            hide_cursor: false,
+        }
+    }
+
+    /// Set the standard stream of the writer, see `ProgressBar::set_stream`.
+    pub fn stream(mut self, stream: Option<Stream>) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Set the style, see `ProgressBar::set_style`.
+    pub fn style(mut self, style: &Style) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    /// Set the message, see `ProgressBar::message`.
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_owned();
+        self
+    }
+
+    /// Set the width, see `ProgressBar::set_width`.
+    pub fn width(mut self, w: Option<usize>) -> Self {
+        self.width = Some(w);
+        self
+    }
+
+    /// Set the max refresh rate, see `ProgressBar::set_max_refresh_rate`.
+    pub fn max_refresh_rate(mut self, w: Option<Duration>) -> Self {
+        self.max_refresh_rate = Some(w);
+        self
+    }
+
+    /// Set the error policy, see `ProgressBar::set_error_policy`.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Set whether to color the end state, see `ProgressBar::set_color`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Set whether to draw JSON lines, see `ProgressBar::set_json`.
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// Set whether to report the progress with `OSC 9;4`, see
+    /// `ProgressBar::set_taskbar`.
+    pub fn taskbar(mut self, taskbar: bool) -> Self {
+        self.taskbar = taskbar;
+        self
+    }
+
+    /// Show the progress in the title of the terminal window, see
+    /// `ProgressBar::set_title`.
+    pub fn title(mut self, label: &str) -> Self {
+        self.title = Some(label.to_owned());
+        self
+    }
+
+    /// Set whether to hide the cursor, see `ProgressBar::set_hide_cursor`.
+    pub fn hide_cursor(mut self, hide: bool) -> Self {
+        self.hide_cursor = hide;
+        self
+    }
+
+    /// Create the bar, or return why it can not be drawn.
+    pub fn build(self) -> Result<ProgressBar<T>, ConfigError> {
+        if self.width == Some(Some(0)) {
+            return Err(ConfigError::Width);
+        }
+        let mut pb = ProgressBar::on(self.handle, self.total);
+        pb.set_stream(self.stream);
+        pb.set_style(&self.style)?;
+        pb.message(&self.message);
+        if let Some(w) = self.width {
+            pb.set_width(w);
+        }
+        if let Some(w) = self.max_refresh_rate {
+            pb.set_max_refresh_rate(w);
+        }
+        pb.set_error_policy(self.error_policy);
+        if let Some(color) = self.color {
+            pb.set_color(color);
+        }
+        pb.set_json(self.json);
+        pb.set_taskbar(self.taskbar);
+        pb.set_title(self.title.as_deref());
+        pb.set_hide_cursor(self.hide_cursor);
+        Ok(pb)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ConfigError, ProgressBarBuilder, Style};
+
+    #[test]
+    fn build() {
+        let style = Style::new().format("|#--|").show_speed(false);
+        let pb = ProgressBarBuilder::on(Vec::new(), 10)
+            .style(&style)
+            .width(Some(40))
+            .build()
+            .unwrap();
+        assert_eq!(pb.style(), &style);
+        assert!(!pb.show_speed());
+
+        let err = ProgressBarBuilder::on(Vec::new(), 10)
+            .width(Some(0))
+            .build()
+            .err();
+        assert_eq!(err, Some(ConfigError::Width));
+        let err = ProgressBarBuilder::on(Vec::new(), 10)
+            .style(&Style::new().format("[]"))
+            .build()
+            .err();
+        assert_eq!(err, Some(ConfigError::Format("[]".to_owned())));
+    }
+}
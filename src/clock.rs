@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// the interval of the ticks.
+pub(crate) const RESOLUTION: Duration = Duration::from_millis(10);
+// the ticks without a reader after which the thread stops.
+const IDLE_TICKS: u64 = 100;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static USED: AtomicBool = AtomicBool::new(false);
+// whether threads can not be spawned on this platform.
+static UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+// ticks returns a count that a background thread increments every
+// `RESOLUTION`, so a bar can tell that time passed with an atomic load
+// instead of reading the clock on every update. The thread is started on
+// demand and stops after a second without readers. Returns `None` if it
+// can not be started.
+pub(crate) fn ticks() -> Option<u64> {
+    USED.store(true, Ordering::Relaxed);
+    if !RUNNING.load(Ordering::Acquire) && !start() {
+        return None;
+    }
+    Some(TICKS.load(Ordering::Relaxed))
+}
+
+fn start() -> bool {
+    if UNSUPPORTED.load(Ordering::Relaxed) {
+        return false;
+    }
+    if RUNNING
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        // started by another reader.
+        return true;
+    }
+    // a new count, so no reader mistakes it for the one it saw last.
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    let spawned = thread::Builder::new()
+        .name("pbr-clock".to_owned())
+        .spawn(run);
+    if spawned.is_err() {
+        UNSUPPORTED.store(true, Ordering::Relaxed);
+        RUNNING.store(false, Ordering::Release);
+        return false;
+    }
+    true
+}
+
+fn run() {
+    let mut idle = 0;
+    loop {
+        thread::sleep(RESOLUTION);
+        TICKS.fetch_add(1, Ordering::Relaxed);
+        if USED.swap(false, Ordering::Relaxed) {
+            idle = 0;
+        } else {
+            idle += 1;
+            if idle == IDLE_TICKS {
+                RUNNING.store(false, Ordering::Release);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ticks, RESOLUTION};
+    use std::thread;
+
+    #[test]
+    fn advance() {
+        let start = ticks().unwrap();
+        thread::sleep(RESOLUTION * 3);
+        assert!(ticks().unwrap() > start, "should count the time");
+    }
+}
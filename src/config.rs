@@ -0,0 +1,97 @@
+use std::env;
+use std::time::Duration;
+
+// Config holds the defaults that are set with environment variables, so
+// bars can be configured without changing the program. It is read when a
+// `ProgressBar` or a `MultiBar` is created, and the setters of the bar
+// still override it:
+//
+// - `PBR_DISABLE`: don't draw the bars, JSON lines are still written.
+// - `PBR_FORCE_TTY`: draw the bars as on a terminal, even if `TERM` is
+//   `dumb` or the handle is not a terminal.
+// - `PBR_WIDTH`: the width of the bars.
+// - `PBR_REFRESH_MS`: the max refresh rate in milliseconds, 0 for none.
+// - `NO_COLOR`: don't color the glyphs of the end states.
+// - `TERM=dumb`: don't draw the bars, the terminal can not redraw them.
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub hidden: bool,
+    pub force_tty: bool,
+    pub width: Option<usize>,
+    pub refresh_rate: Option<Duration>,
+    pub color: bool,
+}
+
+// the defaults without any of the variables set.
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            hidden: false,
+            force_tty: false,
+            width: None,
+            refresh_rate: None,
+            color: true,
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn from_env() -> Config {
+        Config::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Config {
+        let set = |name| matches!(var(name), Some(v) if !v.is_empty() && v != "0");
+        let dumb = var("TERM").as_deref() == Some("dumb");
+        let force_tty = set("PBR_FORCE_TTY");
+        Config {
+            hidden: set("PBR_DISABLE") || (dumb && !force_tty),
+            force_tty,
+            width: var("PBR_WIDTH")
+                .and_then(|w| w.parse().ok())
+                .filter(|&w| w > 0),
+            refresh_rate: var("PBR_REFRESH_MS")
+                .and_then(|ms| ms.parse().ok())
+                .map(Duration::from_millis),
+            color: !matches!(var("NO_COLOR"), Some(v) if !v.is_empty()) && !dumb,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use std::time::Duration;
+
+    fn config(vars: &[(&str, &str)]) -> Config {
+        Config::from_vars(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn from_vars() {
+        let c = config(&[]);
+        assert!(!c.hidden && c.color);
+        assert_eq!((c.width, c.refresh_rate), (None, None));
+
+        let c = config(&[
+            ("PBR_WIDTH", "120"),
+            ("PBR_REFRESH_MS", "200"),
+            ("NO_COLOR", "1"),
+        ]);
+        assert_eq!(c.width, Some(120));
+        assert_eq!(c.refresh_rate, Some(Duration::from_millis(200)));
+        assert!(!c.color);
+
+        assert!(config(&[("PBR_DISABLE", "1")]).hidden);
+        assert!(!config(&[("PBR_DISABLE", "0")]).hidden);
+        let c = config(&[("TERM", "dumb")]);
+        assert!(c.hidden && !c.color);
+        let c = config(&[("TERM", "dumb"), ("PBR_FORCE_TTY", "1")]);
+        assert!(!c.hidden && c.force_tty);
+        assert_eq!(config(&[("PBR_WIDTH", "wide")]).width, None);
+    }
+}
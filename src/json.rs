@@ -0,0 +1,81 @@
+use std::fmt;
+
+// Line is a line of the JSON output of a bar, see `ProgressBar::set_json`.
+pub(crate) struct Line<'a> {
+    pub id: Option<usize>,
+    pub pos: u64,
+    pub total: u64,
+    pub rate: f64,
+    pub eta_secs: Option<f64>,
+    pub msg: &'a str,
+    pub state: &'a str,
+}
+
+impl<'a> fmt::Display for Line<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        if let Some(id) = self.id {
+            write!(f, "\"id\":{},", id)?;
+        }
+        write!(
+            f,
+            "\"pos\":{},\"total\":{},\"rate\":{:.2},",
+            self.pos, self.total, self.rate
+        )?;
+        match self.eta_secs {
+            Some(eta) => write!(f, "\"eta_secs\":{:.0},", eta)?,
+            None => write!(f, "\"eta_secs\":null,")?,
+        }
+        write!(f, "\"msg\":")?;
+        write_str(f, self.msg)?;
+        write!(f, ",\"state\":")?;
+        write_str(f, self.state)?;
+        write!(f, "}}")
+    }
+}
+
+// write `s` as a JSON string.
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::Line;
+
+    #[test]
+    fn format() {
+        let mut line = Line {
+            id: None,
+            pos: 3,
+            total: 10,
+            rate: 1.5,
+            eta_secs: Some(4.66),
+            msg: "say \"hi\"\n",
+            state: "running",
+        };
+        assert_eq!(
+            line.to_string(),
+            r#"{"pos":3,"total":10,"rate":1.50,"eta_secs":5,"msg":"say \"hi\"\n","state":"running"}"#
+        );
+        line.id = Some(2);
+        line.eta_secs = None;
+        line.msg = "\x01";
+        assert_eq!(
+            line.to_string(),
+            r#"{"id":2,"pos":3,"total":10,"rate":1.50,"eta_secs":null,"msg":"\u0001","state":"running"}"#
+        );
+    }
+}
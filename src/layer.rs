@@ -0,0 +1,250 @@
+use crate::multi::BarFactory;
+use crate::{MultiBar, Pipe, ProgressBar};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+// the field of a span that holds the total of its bar.
+const TOTAL: &str = "progress.total";
+// the field of an event that is added to the bar of its span.
+const INC: &str = "progress.inc";
+
+/// ProgressLayer is a `tracing` layer that draws a bar for every span with
+/// a `progress.total` field.
+///
+/// Events with a `progress.inc` field add to the bar of their span, and the
+/// bar is removed when its span closes. A span can record its total later,
+/// if the field is declared as `tracing::field::Empty`.
+///
+/// The bars are drawn by a `MultiBar` that listens in a background thread
+/// while any of them is active.
+///
+/// Available with the `tracing` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::ProgressLayer;
+/// use tracing_subscriber::prelude::*;
+///
+/// tracing_subscriber::registry()
+///     .with(ProgressLayer::new())
+///     .init();
+///
+/// let span = tracing::info_span!("copy", progress.total = 100);
+/// let _enter = span.enter();
+/// for _ in 0..100 {
+///     // ...
+///     tracing::trace!(progress.inc = 1);
+/// }
+/// ```
+pub struct ProgressLayer<T: Write> {
+    mb: Arc<MultiBar<T>>,
+    factory: BarFactory,
+    listening: Arc<AtomicBool>,
+}
+
+// SpanBar is the bar of a span, kept in the extensions of the span.
+struct SpanBar {
+    level: usize,
+    pb: ProgressBar<Pipe>,
+}
+
+impl ProgressLayer<Stdout> {
+    /// Create a new ProgressLayer with stdout as a writer.
+    pub fn new() -> ProgressLayer<Stdout> {
+        ProgressLayer::with(MultiBar::new())
+    }
+}
+
+impl Default for ProgressLayer<Stdout> {
+    fn default() -> Self {
+        ProgressLayer::new()
+    }
+}
+
+impl<T: Write + Send + 'static> ProgressLayer<T> {
+    /// Create a new ProgressLayer with an arbitrary writer.
+    pub fn on(handle: T) -> ProgressLayer<T> {
+        ProgressLayer::with(MultiBar::on(handle))
+    }
+
+    fn with(mb: MultiBar<T>) -> ProgressLayer<T> {
+        let mb = Arc::new(mb);
+        ProgressLayer {
+            factory: mb.factory(),
+            mb,
+            listening: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The `MultiBar` drawing the bars, e.g. to print above them with
+    /// `MultiBar::suspend`.
+    pub fn multibar(&self) -> &Arc<MultiBar<T>> {
+        &self.mb
+    }
+
+    fn create_bar(&self, name: &str, total: u64) -> SpanBar {
+        let (level, mut pb) = self.factory.create_bar(None, total);
+        pb.message(&format!("{} ", name));
+        // events are few, and the MultiBar already coalesces the frames.
+        pb.set_max_refresh_rate(None);
+        pb.add(0);
+        self.listen();
+        SpanBar { level, pb }
+    }
+
+    // listen in a background thread, unless it already does. The thread
+    // exits once all bars are done, and is started again for new bars.
+    fn listen(&self) {
+        if self.listening.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let mb = self.mb.clone();
+        let listening = self.listening.clone();
+        thread::spawn(move || loop {
+            mb.listen();
+            listening.store(false, Ordering::SeqCst);
+            // a bar may have been created after `listen` returned, and
+            // before `listening` was cleared.
+            if mb.factory().active() == 0 || listening.swap(true, Ordering::SeqCst) {
+                break;
+            }
+        });
+    }
+}
+
+impl<S, T> Layer<S> for ProgressLayer<T>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    T: Write + Send + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut total = Value::new(TOTAL);
+        attrs.record(&mut total);
+        if let (Some(total), Some(span)) = (total.value, ctx.span(id)) {
+            let bar = self.create_bar(span.name(), total);
+            span.extensions_mut().insert(bar);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut total = Value::new(TOTAL);
+        values.record(&mut total);
+        if let (Some(total), Some(span)) = (total.value, ctx.span(id)) {
+            let mut ext = span.extensions_mut();
+            match ext.get_mut::<SpanBar>() {
+                Some(bar) => {
+                    bar.pb.set_total(total);
+                    bar.pb.add(0);
+                }
+                None => ext.insert(self.create_bar(span.name(), total)),
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut inc = Value::new(INC);
+        event.record(&mut inc);
+        if let (Some(inc), Some(span)) = (inc.value, ctx.event_span(event)) {
+            if let Some(bar) = span.extensions_mut().get_mut::<SpanBar>() {
+                bar.pb.add(inc);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let bar = ctx
+            .span(&id)
+            .and_then(|span| span.extensions_mut().remove::<SpanBar>());
+        if let Some(mut bar) = bar {
+            // remove the line first, so the drawing of the end state
+            // already clears it.
+            self.factory.layout().lock().unwrap().remove(bar.level);
+            bar.pb.finish();
+        }
+    }
+}
+
+// Value visits the fields of a span or an event, and keeps the value of
+// the field `name` if it is an unsigned integer.
+struct Value {
+    name: &'static str,
+    value: Option<u64>,
+}
+
+impl Value {
+    fn new(name: &'static str) -> Value {
+        Value { name, value: None }
+    }
+}
+
+impl Visit for Value {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == self.name {
+            self.value = Some(value);
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if field.name() == self.name {
+            self.value = u64::try_from(value).ok();
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::fixture::Shared;
+    use crate::{MultiBar, ProgressLayer};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tracing_subscriber::prelude::*;
+
+    // wait until `f` holds, the bars are drawn by another thread.
+    fn wait<F: Fn() -> bool>(f: F) {
+        let start = Instant::now();
+        while !f() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn spans() {
+        let out = Shared::default();
+        let layer = ProgressLayer::with(MultiBar::with_config(out.clone(), Config::default()));
+        let mb = layer.multibar().clone();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        let drawn = |s: &str| out.text().contains(s);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let copy = tracing::info_span!("copy", progress.total = 10);
+            let _enter = copy.enter();
+            tracing::info!(progress.inc = 4);
+            wait(|| drawn("copy 4 / 10"));
+
+            // spans without a total have no bar.
+            tracing::info_span!("plain").in_scope(|| tracing::info!(progress.inc = 1));
+
+            let later = tracing::info_span!("later", progress.total = tracing::field::Empty);
+            later.record("progress.total", 5);
+            later.in_scope(|| tracing::info!(progress.inc = 5));
+            wait(|| drawn("later 5 / 5"));
+        });
+
+        wait(|| mb.summary().succeeded == 2);
+        assert!(!drawn("plain"));
+    }
+}
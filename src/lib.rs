@@ -67,51 +67,111 @@
 //! }
 //! ```
 //!
-//! 3. Broadcast writing(simple file copying)
+//! 3. Reading with progress (simple file copying)
 //!
-//! ```ignore
-//! #![feature(io)]
+//! ```no_run
 //! use std::io::copy;
-//! use std::io::prelude::*;
 //! use std::fs::File;
 //! use pbr::{ProgressBar, Units};
 //!
 //! fn main() {
-//!     let mut file = File::open("/usr/share/dict/words").unwrap();
-//!     let n_bytes = file.metadata().unwrap().len() as usize;
+//!     let file = File::open("/usr/share/dict/words").unwrap();
+//!     let n_bytes = file.metadata().unwrap().len();
 //!     let mut pb = ProgressBar::new(n_bytes);
 //!     pb.set_units(Units::Bytes);
-//!     let mut handle = File::create("copy-words").unwrap().broadcast(&mut pb);
-//!     copy(&mut file, &mut handle).unwrap();
+//!     let mut handle = File::create("copy-words").unwrap();
+//!     copy(&mut pb.wrap_read(file), &mut handle).unwrap();
 //!     pb.finish_print("done");
 //! }
 //! ```
+//!
+//! ### Environment variables
+//!
+//! The defaults of the bars can be changed without changing the program,
+//! with environment variables that are read when a `ProgressBar` or a
+//! `MultiBar` is created:
+//!
+//! - `PBR_DISABLE=1`: don't draw the bars, the text of `finish_print`
+//!   and `finish_println` and the JSON lines are still written.
+//! - `PBR_FORCE_TTY=1`: draw the bars as on a terminal, even on a dumb
+//!   terminal or a handle that is not one.
+//! - `PBR_WIDTH=120`: the width of the bars, instead of the width of the
+//!   terminal.
+//! - `PBR_REFRESH_MS=200`: the max refresh rate, `0` to draw every update.
+//! - `NO_COLOR`: don't color the glyphs of the end states.
+//! - `TERM=dumb`: don't draw the bars, unless `PBR_FORCE_TTY` is set. The
+//!   JSON lines are still written.
+//!
+//! Setting the same options in the program overrides them.
 
-// Macro for writing to the giving writer.
+// Macro for writing to the giving writer and flushing it.
+// Returns the `io::Result` of the write.
 // Used in both pb.rs and multi.rs modules.
 //
 // # Examples
 //
 // ```
 // let w = io::stdout();
-// printfl!(w, "");
-// printfl!(w, "\r{}", out);
+// printfl!(w, "")?;
+// printfl!(w, "\r{}", out)?;
 //
 // ```
 macro_rules! printfl {
    ($w:expr, $($tt:tt)*) => {{
-        $w.write_all(&format!($($tt)*).as_bytes()).ok().expect("write() fail");
-        $w.flush().ok().expect("flush() fail");
+        $w.write_all(&format!($($tt)*).as_bytes())
+            .and_then(|_| $w.flush())
     }}
 }
 
+#[cfg(any(feature = "futures", feature = "tokio"))]
+mod async_io;
+mod builder;
+mod clock;
+mod config;
+#[cfg(test)]
+mod fixture;
+mod json;
+#[cfg(feature = "tracing")]
+mod layer;
+#[cfg(feature = "log")]
+mod logger;
 mod multi;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod pb;
+mod reader;
+mod spinner;
+mod style;
+mod title;
+mod tree;
 mod tty;
-pub use multi::{MultiBar, Pipe};
-pub use pb::{ProgressBar, Units};
-use std::io::{stdout, Stdout, Write};
+mod writer;
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub use async_io::{ProgressAsyncRead, ProgressAsyncWrite};
+#[cfg(feature = "futures")]
+pub use async_io::{ProgressStream, ProgressStreamExt};
+pub use builder::ProgressBarBuilder;
+#[cfg(feature = "tracing")]
+pub use layer::ProgressLayer;
+#[cfg(feature = "log")]
+pub use logger::{LogWrapper, Suspend};
+pub use multi::{MultiBar, Pipe, Summary};
+#[cfg(feature = "rayon")]
+pub use parallel::{ParallelProgressIterator, ProgressParIter};
+pub use pb::{EndState, ErrorPolicy, ProgressBar, Units};
+pub use reader::ProgressReader;
+pub use spinner::{Spinner, Spinning};
+use std::io::{Stdout, Write};
+use std::iter::FusedIterator;
+pub use style::{ConfigError, Style};
+pub use tree::ProgressNode;
+pub use tty::{is_terminal, show_cursor_on_signal, terminal_size, Height, Stream, Width};
+pub use writer::ProgressWriter;
 
+/// PbIter wraps an iterator and increments a `ProgressBar` for every
+/// item, see `ProgressIterator`.
+///
+/// The bar is finished when the iterator is exhausted.
 pub struct PbIter<T, I>
 where
     I: Iterator,
@@ -126,7 +186,11 @@ where
     I: Iterator,
 {
     pub fn new(iter: I) -> Self {
-        Self::on(stdout(), iter)
+        let total = size(&iter);
+        PbIter {
+            iter,
+            progress_bar: ProgressBar::new(total),
+        }
     }
 }
 
@@ -135,11 +199,45 @@ where
     I: Iterator,
     T: Write,
 {
+    /// Create a PbIter with an arbitrary writer. The total is the upper
+    /// bound of the iterator size hint, or its lower bound if there is none.
     pub fn on(handle: T, iter: I) -> Self {
-        let size = iter.size_hint().0;
+        let total = size(&iter);
         PbIter {
             iter,
-            progress_bar: ProgressBar::on(handle, size as u64),
+            progress_bar: ProgressBar::on(handle, total),
+        }
+    }
+
+    /// Get a mutable reference to the progress bar, e.g. to update
+    /// its message while iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressIterator;
+    ///
+    /// let mut it = (0..100).progress();
+    /// while let Some(i) = it.next() {
+    ///     it.progress_bar().message(&format!("item {} ", i));
+    /// }
+    /// ```
+    pub fn progress_bar(&mut self) -> &mut ProgressBar<T> {
+        &mut self.progress_bar
+    }
+
+    fn step(&mut self, item: Option<I::Item>) -> Option<I::Item> {
+        match item {
+            Some(i) => {
+                self.progress_bar.inc();
+                Some(i)
+            }
+            None => {
+                if !self.progress_bar.is_finish() {
+                    self.progress_bar.finish();
+                }
+                None
+            }
         }
     }
 }
@@ -152,16 +250,92 @@ where
     type Item = I::Item;
 
     fn next(&mut self) -> Option<I::Item> {
-        match self.iter.next() {
-            Some(i) => {
-                self.progress_bar.inc();
-                Some(i)
-            }
-            None => None,
-        }
+        let item = self.iter.next();
+        self.step(item)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         self.iter.size_hint()
     }
 }
+
+impl<T, I> DoubleEndedIterator for PbIter<T, I>
+where
+    I: DoubleEndedIterator,
+    T: Write,
+{
+    fn next_back(&mut self) -> Option<I::Item> {
+        let item = self.iter.next_back();
+        self.step(item)
+    }
+}
+
+impl<T, I> ExactSizeIterator for PbIter<T, I>
+where
+    I: ExactSizeIterator,
+    T: Write,
+{
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<T, I> FusedIterator for PbIter<T, I>
+where
+    I: FusedIterator,
+    T: Write,
+{
+}
+
+/// ProgressIterator adds progress bars to any `Iterator`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::{ProgressBar, ProgressIterator};
+/// use std::io::stderr;
+///
+/// for _ in (0..1000).progress() {
+///     // ...
+/// }
+///
+/// let mut pb = ProgressBar::on(stderr(), 0);
+/// pb.format("╢▌▌░╟");
+/// for _ in vec![1, 2, 3].into_iter().progress_with(pb) {
+///     // ...
+/// }
+/// ```
+pub trait ProgressIterator: Iterator + Sized {
+    /// Wrap the iterator with a new `ProgressBar` on stdout.
+    fn progress(self) -> PbIter<Stdout, Self> {
+        PbIter::new(self)
+    }
+
+    /// Wrap the iterator with the given `ProgressBar`.
+    ///
+    /// If the total of the bar is `0`, it is set from the size hint of
+    /// the iterator, like in `PbIter::on`.
+    fn progress_with<T: Write>(self, mut pb: ProgressBar<T>) -> PbIter<T, Self> {
+        if pb.total() == 0 {
+            pb.set_total(size(&self));
+        }
+        PbIter {
+            iter: self,
+            progress_bar: pb,
+        }
+    }
+
+    /// Wrap the iterator with a new `ProgressBar` on stdout with the
+    /// given total, for iterators without a useful size hint.
+    fn progress_count(self, total: u64) -> PbIter<Stdout, Self> {
+        self.progress_with(ProgressBar::new(total))
+    }
+}
+
+impl<I: Iterator> ProgressIterator for I {}
+
+// the expected number of items of `iter`.
+fn size<I: Iterator>(iter: &I) -> u64 {
+    let (lower, upper) = iter.size_hint();
+    upper.unwrap_or(lower) as u64
+}
@@ -0,0 +1,169 @@
+use crate::{MultiBar, ProgressBar};
+use log::{Log, Metadata, Record, SetLoggerError};
+use std::io::Write;
+use std::sync::{Arc, Mutex, TryLockError};
+
+/// Suspend is implemented by the bars a `LogWrapper` hides while a
+/// record is logged.
+///
+/// Available with the `log` feature.
+pub trait Suspend: Send + Sync {
+    /// Hide the bars while `f` runs, and draw them again after.
+    fn suspend(&self, f: &mut dyn FnMut());
+}
+
+// the record is logged without hiding the bar if the lock is held, e.g.
+// by the thread logging it, which would deadlock waiting for it.
+impl<T: Write + Send> Suspend for Mutex<ProgressBar<T>> {
+    fn suspend(&self, f: &mut dyn FnMut()) {
+        match self.try_lock() {
+            Ok(mut pb) => pb.suspend(f),
+            Err(TryLockError::Poisoned(e)) => e.into_inner().suspend(f),
+            Err(TryLockError::WouldBlock) => f(),
+        }
+    }
+}
+
+impl<T: Write + Send> Suspend for MultiBar<T> {
+    fn suspend(&self, f: &mut dyn FnMut()) {
+        MultiBar::suspend(self, f)
+    }
+}
+
+impl<S: Suspend + ?Sized> Suspend for Arc<S> {
+    fn suspend(&self, f: &mut dyn FnMut()) {
+        (**self).suspend(f)
+    }
+}
+
+/// LogWrapper wraps a logger, and hides the bars while it logs a record,
+/// so log lines are printed above the bars instead of tearing them.
+///
+/// Available with the `log` feature.
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::sync::Arc;
+/// use pbr::{LogWrapper, MultiBar};
+///
+/// let logger = env_logger::Builder::from_default_env().build();
+/// let level = logger.filter();
+/// let mb = Arc::new(MultiBar::new());
+/// LogWrapper::new(logger, mb.clone()).try_init().unwrap();
+/// log::set_max_level(level);
+///
+/// let mut pb = mb.create_bar(10);
+/// // ...
+/// log::info!("this line is printed above the bars");
+/// ```
+pub struct LogWrapper<L, S> {
+    inner: L,
+    bars: S,
+}
+
+impl<L: Log, S: Suspend> LogWrapper<L, S> {
+    /// Wrap the `inner` logger, `bars` is an `Arc<MultiBar<_>>` or an
+    /// `Arc<Mutex<ProgressBar<_>>>` that is shared with the code updating
+    /// the bars. A record logged while the `Mutex` is locked, e.g. by the
+    /// logging thread itself, is logged without hiding the bar.
+    pub fn new(inner: L, bars: S) -> Self {
+        LogWrapper { inner, bars }
+    }
+
+    /// Set the wrapper as the global logger.
+    ///
+    /// Like `log::set_boxed_logger`, it does not change the max level of
+    /// the `log` crate, set it with `log::set_max_level`.
+    pub fn try_init(self) -> Result<(), SetLoggerError>
+    where
+        L: 'static,
+        S: 'static,
+    {
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl<L: Log, S: Suspend> Log for LogWrapper<L, S> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        // skip the redrawing of the bars for records that are filtered out.
+        if self.inner.enabled(record.metadata()) {
+            self.bars.suspend(&mut || self.inner.log(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::{LogWrapper, ProgressBar};
+    use log::{Level, Log, Metadata, Record};
+    use std::sync::{Arc, Mutex};
+
+    // Lines collects the messages of the records it logs.
+    #[derive(Clone, Default)]
+    struct Lines(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Lines {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn wrap_progress_bar() {
+        let lines = Lines::default();
+        let pb = Arc::new(Mutex::new(ProgressBar::with_config(
+            Vec::new(),
+            10,
+            Config::default(),
+        )));
+        pb.lock().unwrap().inc();
+        let logger = LogWrapper::new(lines.clone(), pb.clone());
+
+        for level in [Level::Info, Level::Debug] {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("{}", level))
+                    .build(),
+            );
+        }
+        assert_eq!(*lines.0.lock().unwrap(), ["INFO"], "should filter records");
+        assert_eq!(pb.lock().unwrap().current, 1);
+    }
+
+    #[test]
+    fn log_while_locked() {
+        let lines = Lines::default();
+        let pb = Arc::new(Mutex::new(ProgressBar::with_config(
+            Vec::new(),
+            10,
+            Config::default(),
+        )));
+        let logger = LogWrapper::new(lines.clone(), pb.clone());
+
+        let _guard = pb.lock().unwrap();
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("locked"))
+                .build(),
+        );
+        assert_eq!(*lines.0.lock().unwrap(), ["locked"], "should not deadlock");
+    }
+}
@@ -1,21 +1,309 @@
-use crate::tty::move_cursor_up;
-use crate::ProgressBar;
-use crossbeam_channel::{unbounded, Receiver, Sender};
-use std::io::{Result, Stdout, Write};
-use std::str::from_utf8;
+use crate::config::Config;
+use crate::style::visible_width;
+use crate::tty::{clear_line, is_terminal, move_cursor_up, CursorGuard, Stream};
+use crate::{ConfigError, EndState, ErrorPolicy, ProgressBar, ProgressNode, Style};
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use std::fmt;
+use std::io::{Error, ErrorKind, Result, Stdout, Write};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 pub struct MultiBar<T: Write> {
-    state: Mutex<State<T>>,
+    out: Mutex<Output<T>>,
+    layout: Arc<Mutex<Layout>>,
     chan: (Sender<WriteMsg>, Receiver<WriteMsg>),
-    nbars: AtomicUsize,
+    nbars: Arc<AtomicUsize>,
+    error_policy: ErrorPolicy,
+    json: bool,
+    style: Style,
+    stream: Option<Stream>,
+    hide_cursor: bool,
+    // whether drawing is disabled by the environment, see `Config`.
+    hidden: bool,
+    max_refresh_rate: Option<Duration>,
+    summary: Mutex<Summary>,
+    // the defaults of the bars that are created.
+    config: Config,
 }
 
-struct State<T: Write> {
-    lines: Vec<String>,
-    nlines: usize,
+// Output is the handle of the MultiBar, together with the lines of the
+// last drawing on it, so only the lines that changed are drawn again.
+struct Output<T: Write> {
     handle: T,
+    drawn: Vec<String>,
+    // whether the handle is a terminal, see `overwrite`.
+    tty: bool,
+    // the console the cursor is moved on, on windows.
+    console: Stream,
+}
+
+impl<T: Write> Output<T> {
+    // draw the visible lines of `layout` over the last drawing, rewriting
+    // only the lines that changed since.
+    fn draw(&mut self, layout: &Layout) -> Result<()> {
+        let lines: Vec<&str> = layout
+            .lines
+            .iter()
+            .filter(|l| !l.hidden)
+            .map(|l| l.text.as_str())
+            .collect();
+        let drawn = std::mem::take(&mut self.drawn);
+        let nrows = lines.len().max(drawn.len());
+        let mut out = String::new();
+        // the cursor is on the row right below the last drawing.
+        let mut row = drawn.len();
+        for i in 0..nrows {
+            let line = lines.get(i).copied();
+            let prev = drawn.get(i).map(|s| s.as_str());
+            if line.is_some() && prev == line {
+                continue;
+            }
+            self.move_to(&mut out, row, i)?;
+            row = i;
+            self.overwrite(&mut out, prev.unwrap_or(""), line.unwrap_or(""))?;
+        }
+        // leave the cursor right below the last line, so the next drawing
+        // (or whatever is printed after the bars) starts there.
+        self.move_to(&mut out, row, lines.len())?;
+        if !out.is_empty() {
+            printfl!(self.handle, "{}", out)?;
+        }
+
+        self.drawn.extend(lines.iter().map(|l| l.to_string()));
+        Ok(())
+    }
+
+    // clear the last drawing, and leave the cursor at its first line.
+    fn clear(&mut self) -> Result<()> {
+        let drawn = std::mem::take(&mut self.drawn);
+        let n = drawn.len();
+        let mut out = String::new();
+        self.move_to(&mut out, n, 0)?;
+        for (i, prev) in drawn.iter().enumerate() {
+            self.overwrite(&mut out, prev, "")?;
+            self.move_to(&mut out, i, i + 1)?;
+        }
+        self.move_to(&mut out, n, 0)?;
+        out += "\r";
+        printfl!(self.handle, "{}", out)
+    }
+
+    // append the moving of the cursor from row `from` to row `to` to
+    // `out`. Moving down prints new lines, so it also adds rows below
+    // the drawing.
+    fn move_to(&mut self, out: &mut String, from: usize, to: usize) -> Result<()> {
+        if to < from {
+            self.pending(out)?;
+            *out += &move_cursor_up(self.console, from - to);
+        } else {
+            *out += &"\n".repeat(to - from);
+        }
+        Ok(())
+    }
+
+    // append the drawing of `line` over `prev`, the line of the cursor,
+    // to `out`. A terminal erases the line first, on other handles spaces
+    // cover what is left of `prev`.
+    fn overwrite(&mut self, out: &mut String, prev: &str, line: &str) -> Result<()> {
+        if self.tty {
+            self.pending(out)?;
+            *out += &clear_line(self.console);
+        }
+        *out += "\r";
+        *out += line;
+        if !self.tty {
+            let pad = visible_width(prev).saturating_sub(visible_width(line));
+            *out += &" ".repeat(pad);
+        }
+        Ok(())
+    }
+
+    // on windows the cursor is moved and the line is cleared right away,
+    // so what is in `out` must be written first.
+    fn pending(&mut self, out: &mut String) -> Result<()> {
+        if cfg!(windows) && !out.is_empty() {
+            printfl!(self.handle, "{}", out)?;
+            out.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Summary of the end states of the bars of a `MultiBar`, see
+/// `MultiBar::summary`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub succeeded: usize,
+    pub warned: usize,
+    pub failed: usize,
+    pub abandoned: usize,
+}
+
+impl Summary {
+    fn add(&mut self, state: EndState) {
+        match state {
+            EndState::Success => self.succeeded += 1,
+            EndState::Warning => self.warned += 1,
+            EndState::Error => self.failed += 1,
+            EndState::Abandoned => self.abandoned += 1,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} succeeded, {} with warnings, {} failed, {} abandoned",
+            self.succeeded, self.warned, self.failed, self.abandoned
+        )
+    }
+}
+
+// Layout holds the lines of the MultiBar in display order. Each line is
+// identified by its level, which is stable even if lines are inserted
+// before it (see `ProgressNode::create_child`).
+pub(crate) struct Layout {
+    lines: Vec<Line>,
+    nlevels: usize,
+}
+
+struct Line {
+    level: usize,
+    text: String,
+    hidden: bool,
+    frame: Frame,
+}
+
+// Frame holds the newest frame written by a bar that was not drawn yet.
+type Frame = Arc<Mutex<Option<String>>>;
+
+impl Layout {
+    // push a line at the bottom of the layout and return its level.
+    fn push(&mut self, text: String) -> usize {
+        let pos = self.lines.len();
+        self.insert(pos, text)
+    }
+
+    // insert a line at display position `pos` and return its level.
+    fn insert(&mut self, pos: usize, text: String) -> usize {
+        let level = self.nlevels;
+        self.nlevels += 1;
+        self.lines.insert(
+            pos,
+            Line {
+                level,
+                text,
+                hidden: false,
+                frame: Frame::default(),
+            },
+        );
+        level
+    }
+
+    // take the pending frame of `level`, if any, as the line text, and
+    // return the new text.
+    fn update(&mut self, level: usize) -> Option<&str> {
+        let pos = self.position(level)?;
+        let line = &mut self.lines[pos];
+        let text = line.frame.lock().unwrap().take()?;
+        line.text = text;
+        Some(&line.text)
+    }
+
+    fn position(&self, level: usize) -> Option<usize> {
+        self.lines.iter().position(|l| l.level == level)
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn remove(&mut self, level: usize) {
+        if let Some(pos) = self.position(level) {
+            self.lines.remove(pos);
+        }
+    }
+
+    pub(crate) fn set_hidden(&mut self, level: usize, hidden: bool) {
+        if let Some(pos) = self.position(level) {
+            self.lines[pos].hidden = hidden;
+        }
+    }
+}
+
+// BarFactory creates bars that draw into a MultiBar. It does not
+// depend on the MultiBar writer, so it can be shared with
+// `ProgressNode`s that outlive the borrow of the MultiBar.
+#[derive(Clone)]
+pub(crate) struct BarFactory {
+    layout: Arc<Mutex<Layout>>,
+    chan: Sender<WriteMsg>,
+    nbars: Arc<AtomicUsize>,
+    json: bool,
+    style: Style,
+    stream: Option<Stream>,
+    config: Config,
+}
+
+impl BarFactory {
+    // create a bar whose line is placed right after the line of
+    // `after`, or at the bottom of the MultiBar if `after` is `None`.
+    // returns the level of the new line together with the bar.
+    pub(crate) fn create_bar(
+        &self,
+        after: Option<usize>,
+        total: u64,
+    ) -> (usize, ProgressBar<Pipe>) {
+        let (level, frame) = {
+            let mut layout = self.layout.lock().unwrap();
+            let level = match after.and_then(|l| layout.position(l)) {
+                Some(pos) => layout.insert(pos + 1, String::new()),
+                None => layout.push(String::new()),
+            };
+            let pos = layout.position(level).unwrap();
+            (level, layout.lines[pos].frame.clone())
+        };
+
+        self.nbars.fetch_add(1, Ordering::SeqCst);
+
+        // the MultiBar hides the bars itself.
+        let config = Config {
+            hidden: false,
+            ..self.config.clone()
+        };
+        let mut p = ProgressBar::with_config(
+            Pipe {
+                level,
+                chan: self.chan.clone(),
+                frame,
+            },
+            total,
+            config,
+        );
+
+        p.is_multibar = true;
+        p.set_stream(self.stream);
+        // checked by `MultiBar::set_style`.
+        p.style = self.style.clone();
+        p.set_json(self.json);
+        p.id = Some(level);
+        p.notifier = Some(Notifier {
+            level,
+            chan: self.chan.clone(),
+            sent: false,
+        });
+        p.add(0);
+        (level, p)
+    }
+
+    pub(crate) fn layout(&self) -> &Mutex<Layout> {
+        &self.layout
+    }
+
+    // the number of bars that are not done yet.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn active(&self) -> usize {
+        self.nbars.load(Ordering::SeqCst)
+    }
 }
 
 impl MultiBar<Stdout> {
@@ -60,13 +348,24 @@ impl MultiBar<Stdout> {
     /// mb.listen();
     /// ```
     pub fn new() -> MultiBar<Stdout> {
-        MultiBar::on(::std::io::stdout())
+        let mut mb = MultiBar::on(::std::io::stdout());
+        mb.set_stream(Some(Stream::Stdout));
+        mb
+    }
+}
+
+impl Default for MultiBar<Stdout> {
+    fn default() -> Self {
+        MultiBar::new()
     }
 }
 
 impl<T: Write> MultiBar<T> {
     /// Create a new MultiBar with an arbitrary writer.
     ///
+    /// The lines are padded with spaces instead of erased, unless the
+    /// handle writes to a standard stream that is set with `set_stream`.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -79,17 +378,129 @@ impl<T: Write> MultiBar<T> {
     /// // ...
     /// ```
     pub fn on(handle: T) -> MultiBar<T> {
+        MultiBar::with_config(handle, Config::from_env())
+    }
+
+    // create a MultiBar with the defaults of `config` instead of the ones
+    // of the environment.
+    pub(crate) fn with_config(handle: T, config: Config) -> MultiBar<T> {
         MultiBar {
-            state: Mutex::new(State {
-                lines: Vec::new(),
+            out: Mutex::new(Output {
                 handle,
-                nlines: 0,
+                drawn: Vec::new(),
+                tty: config.force_tty,
+                console: Stream::Stdout,
             }),
+            layout: Arc::new(Mutex::new(Layout {
+                lines: Vec::new(),
+                nlevels: 0,
+            })),
             chan: unbounded(),
-            nbars: AtomicUsize::new(0),
+            nbars: Arc::new(AtomicUsize::new(0)),
+            error_policy: ErrorPolicy::Disable,
+            json: false,
+            style: Style::new(),
+            stream: None,
+            hide_cursor: false,
+            hidden: config.hidden,
+            max_refresh_rate: config.refresh_rate,
+            summary: Mutex::new(Summary::default()),
+            config,
         }
     }
 
+    /// Set what `listen` does when writing to the handle fails, default is
+    /// `ErrorPolicy::Disable`.
+    ///
+    /// With `ErrorPolicy::Disable`, `listen` stops drawing but still waits
+    /// for all bars to finish.
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    /// Set whether to write the bars as JSON lines instead of drawing
+    /// them, default is `false`. It applies to the bars created after it.
+    ///
+    /// Each frame of a bar is written as a line, see
+    /// `ProgressBar::set_json`, with the `id` of the bar added. Text lines
+    /// added with `println` are not written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    ///
+    /// let mut mb = MultiBar::new();
+    /// mb.set_json(true);
+    /// let mut pb = mb.create_bar(10);
+    /// // ...
+    /// mb.listen();
+    /// ```
+    pub fn set_json(&mut self, json: bool) {
+        self.json = json;
+    }
+
+    /// Set the style of the bars created afterwards, or return why it can
+    /// not be drawn, default is `Style::new()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::{MultiBar, Style};
+    ///
+    /// let mut mb = MultiBar::new();
+    /// mb.set_style(&Style::cargo()).unwrap();
+    /// let mut pb = mb.create_bar(10);
+    /// // ...
+    /// mb.listen();
+    /// ```
+    pub fn set_style(&mut self, style: &Style) -> std::result::Result<(), ConfigError> {
+        style.validate()?;
+        self.style = style.clone();
+        Ok(())
+    }
+
+    /// Set the standard stream the handle writes to, or `None` if it is
+    /// not one, default is `Stdout` for `new` and `None` for `on`. It
+    /// applies to the bars created after it, see `ProgressBar::set_stream`.
+    pub fn set_stream(&mut self, stream: Option<Stream>) {
+        self.stream = stream;
+        let out = self.out.get_mut().unwrap();
+        out.tty = self.config.force_tty || matches!(stream, Some(s) if is_terminal(s));
+        out.console = stream.unwrap_or(Stream::Stdout);
+    }
+
+    /// Set whether to hide the cursor of the terminal while `listen`
+    /// draws the bars, default is `false`.
+    ///
+    /// It is only hidden when the bars are drawn on a terminal. The cursor
+    /// is shown again when `listen` returns, and also if the program panics
+    /// before that, see `show_cursor_on_signal` for signals.
+    pub fn set_hide_cursor(&mut self, hide: bool) {
+        self.hide_cursor = hide;
+    }
+
+    /// Set max refresh rate of `listen`, or `None` for none.
+    ///
+    /// The updates of all bars that arrive within `w` of the last drawing
+    /// are drawn together in the next one, while the end state of a bar
+    /// is drawn right away. It has no effect in JSON mode, where every
+    /// frame is written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    /// use std::time::Duration;
+    ///
+    /// let mut mb = MultiBar::new();
+    /// // draw at most 20 frames per second.
+    /// mb.set_max_refresh_rate(Some(Duration::from_millis(50)));
+    /// ```
+    pub fn set_max_refresh_rate(&mut self, w: Option<Duration>) {
+        self.max_refresh_rate = w;
+    }
+
     /// println used to add text lines between the bars.
     /// for example: you could add a header to your application,
     /// or text separators between bars.
@@ -118,9 +529,7 @@ impl<T: Write> MultiBar<T> {
     /// mb.listen();
     /// ```
     pub fn println(&self, s: &str) {
-        let mut state = self.state.lock().unwrap();
-        state.lines.push(s.to_owned());
-        state.nlines += 1;
+        self.layout.lock().unwrap().push(s.to_owned());
     }
 
     /// create_bar creates new `ProgressBar` with `Pipe` as the writer.
@@ -130,7 +539,8 @@ impl<T: Write> MultiBar<T> {
     /// you get a progress bar in level 2, and so on.
     ///
     /// ProgressBar that finish its work, must call `finish()` (or `finish_print`)
-    /// to notify the `MultiBar` about it.
+    /// to notify the `MultiBar` about it. A bar that is dropped before it
+    /// finished is abandoned, and its line is left as it was last drawn.
     ///
     /// # Examples
     ///
@@ -155,24 +565,46 @@ impl<T: Write> MultiBar<T> {
     /// mb.listen();
     /// ```
     pub fn create_bar(&self, total: u64) -> ProgressBar<Pipe> {
-        let mut state = self.state.lock().unwrap();
-
-        state.lines.push(String::new());
-        state.nlines += 1;
-
-        self.nbars.fetch_add(1, Ordering::SeqCst);
+        self.factory().create_bar(None, total).1
+    }
 
-        let mut p = ProgressBar::on(
-            Pipe {
-                level: state.nlines - 1,
-                chan: self.chan.0.clone(),
-            },
-            total,
-        );
+    /// create_tree creates the root `ProgressNode` of a tree of bars.
+    ///
+    /// Children created with `ProgressNode::create_child` are drawn
+    /// below their parent and indented by their depth, and each child
+    /// contributes its weight to the progress of its parent.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    ///
+    /// let mut mb = MultiBar::new();
+    ///
+    /// let root = mb.create_tree(100);
+    /// root.message("pipeline ");
+    ///
+    /// // stage 1 is 30% of the work, stage 2 is the other 70%.
+    /// let stage1 = root.create_child(0.3, 10);
+    /// let stage2 = root.create_child(0.7, 1000);
+    /// // ...
+    ///
+    /// mb.listen();
+    /// ```
+    pub fn create_tree(&self, total: u64) -> ProgressNode {
+        ProgressNode::root(self.factory(), total)
+    }
 
-        p.is_multibar = true;
-        p.add(0);
-        p
+    pub(crate) fn factory(&self) -> BarFactory {
+        BarFactory {
+            layout: self.layout.clone(),
+            chan: self.chan.0.clone(),
+            nbars: self.nbars.clone(),
+            json: self.json,
+            style: self.style.clone(),
+            stream: self.stream,
+            config: self.config.clone(),
+        }
     }
 
     /// listen start listen to all bars changes.
@@ -181,7 +613,9 @@ impl<T: Write> MultiBar<T> {
     /// to notify the `MultiBar` about it.
     ///
     /// This is a blocking operation and blocks until all bars will
-    /// finish.
+    /// finish (or be dropped).
+    /// Only the newest frame of each bar is drawn, frames written while the
+    /// `MultiBar` is busy drawing are skipped.
     /// To ignore blocking, you can run it in a different thread.
     ///
     /// # Examples
@@ -204,53 +638,184 @@ impl<T: Write> MultiBar<T> {
     /// // ...
     /// ```
     pub fn listen(&self) {
-        let mut first = true;
-        let mut out = String::new();
+        let _ = self.run(|e| Ok(self.error_policy.handle(e)));
+    }
+
+    /// The end states of the bars that are done, as received by `listen`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    ///
+    /// let mb = MultiBar::new();
+    /// // ...
+    /// mb.listen();
+    /// println!("{}", mb.summary());
+    /// ```
+    pub fn summary(&self) -> Summary {
+        *self.summary.lock().unwrap()
+    }
+
+    /// Like `listen`, but stop listening and return the first error of
+    /// writing to the handle.
+    ///
+    /// Bars that were not done yet keep their state, so `listen` (or
+    /// `try_listen`) can be called again later.
+    pub fn try_listen(&self) -> Result<()> {
+        self.run(Err)
+    }
+
+    /// Hide the bars while `f` runs, and draw them again below what `f`
+    /// printed.
+    ///
+    /// Use it to print to the terminal while `listen` is drawing,
+    /// without leaving half-drawn bars behind. Outside of `listen`, `f`
+    /// just runs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    ///
+    /// let mb = MultiBar::new();
+    /// // ...
+    /// mb.suspend(|| eprintln!("downloading the index failed, retrying"));
+    /// ```
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        // same locking order as `run`.
+        let layout = self.layout.lock().unwrap();
+        let mut out = self.out.lock().unwrap();
+        if out.drawn.is_empty() {
+            return f();
+        }
+        // write errors are left to `listen`, which hits them on its
+        // next drawing.
+        let _ = out.clear();
+        let r = f();
+        let _ = out.draw(&layout);
+        r
+    }
+
+    // run receives messages and draws the bars until all of them are
+    // done. `on_error` is called with write errors, and returns whether
+    // to stop drawing, or the error to stop listening with.
+    fn run<F>(&self, mut on_error: F) -> Result<()>
+    where
+        F: FnMut(Error) -> Result<bool>,
+    {
+        // the environment only hides the drawing, not the JSON lines.
+        let mut disabled = self.hidden && !self.json;
+        let mut last_draw = None;
+        let tty = self.out.lock().unwrap().tty;
+        let _cursor = if self.hide_cursor && tty && !self.json && !self.hidden {
+            self.stream.map(CursorGuard::hide)
+        } else {
+            None
+        };
+        // start a new drawing below what was printed since the last one.
+        self.out.lock().unwrap().drawn.clear();
 
         while self.nbars.load(Ordering::SeqCst) > 0 {
-            // receive message
-            let msg = self.chan.1.recv().unwrap();
-            if msg.done {
-                self.nbars.fetch_sub(1, Ordering::SeqCst);
+            // receive a message, and the ones that are pending after it,
+            // so they are drawn in a single frame.
+            let msg = match self.chan.1.recv() {
+                Ok(msg) => msg,
+                Err(_) => break,
+            };
+            let mut frames = String::new();
+            let mut done = self.receive(msg, &mut frames);
+            while let Ok(msg) = self.chan.1.try_recv() {
+                done |= self.receive(msg, &mut frames);
+            }
+            // wait for the next frame, unless a bar is done.
+            if let (Some(mrr), Some(last), false) = (self.max_refresh_rate, last_draw, self.json) {
+                let deadline = last + mrr;
+                while !done {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    match self.chan.1.recv_timeout(deadline - now) {
+                        Ok(msg) => done = self.receive(msg, &mut frames),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            }
+            if disabled {
                 continue;
             }
 
-            out.clear();
-            let mut state = self.state.lock().unwrap();
-            state.lines[msg.level] = msg.string;
-
-            // and draw
-            if !first {
-                out += &move_cursor_up(state.nlines);
+            let layout = self.layout.lock().unwrap();
+            let mut out = self.out.lock().unwrap();
+            let r = if self.json {
+                // the frames are appended as is.
+                if frames.is_empty() {
+                    Ok(())
+                } else {
+                    printfl!(out.handle, "{}", frames)
+                }
             } else {
-                first = false;
+                out.draw(&layout)
+            };
+            last_draw = Some(Instant::now());
+            if let Err(e) = r {
+                out.drawn.clear();
+                disabled = on_error(e)?;
             }
+        }
+        // the bars are done, `suspend` must not clear them anymore.
+        self.out.lock().unwrap().drawn.clear();
+        Ok(())
+    }
 
-            for l in state.lines.iter() {
-                out.push_str(&format!("\r{}\n", l));
+    // apply `msg` to the layout, and return whether a bar is done. In
+    // JSON mode, the new frame of the bar is appended to `frames`.
+    fn receive(&self, msg: WriteMsg, frames: &mut String) -> bool {
+        let (level, done) = match msg {
+            WriteMsg::Draw(level) => (level, false),
+            // draw anyway, the layout may have changed.
+            WriteMsg::Finish(level, state) => {
+                self.summary.lock().unwrap().add(state);
+                self.nbars.fetch_sub(1, Ordering::SeqCst);
+                (level, true)
+            }
+            WriteMsg::Abandon(level) => {
+                self.summary.lock().unwrap().add(EndState::Abandoned);
+                self.nbars.fetch_sub(1, Ordering::SeqCst);
+                (level, true)
+            }
+        };
+        if let Some(text) = self.layout.lock().unwrap().update(level) {
+            if self.json {
+                *frames += text;
             }
-
-            printfl!(state.handle, "{}", out);
         }
+        done
     }
 }
 
+/// Pipe is the writer of bars created by a `MultiBar`.
+///
+/// Only the newest frame written to a `Pipe` is kept until the `MultiBar`
+/// draws it, so a slow terminal never accumulates a backlog of stale frames.
 pub struct Pipe {
     level: usize,
     chan: Sender<WriteMsg>,
+    frame: Frame,
 }
 
 impl Write for Pipe {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        let s = from_utf8(buf).unwrap().to_owned();
-        self.chan
-            .send(WriteMsg {
-                // finish method emit empty string
-                done: s.is_empty(),
-                level: self.level,
-                string: s,
-            })
-            .unwrap();
+        let s = String::from_utf8_lossy(buf).into_owned();
+        // notify the MultiBar only if it already took the previous
+        // frame, otherwise the pending one is just replaced.
+        if self.frame.lock().unwrap().replace(s).is_none() {
+            self.chan
+                .send(WriteMsg::Draw(self.level))
+                .map_err(|_| Error::new(ErrorKind::BrokenPipe, "MultiBar was dropped"))?;
+        }
         Ok(buf.len())
     }
 
@@ -259,10 +824,314 @@ impl Write for Pipe {
     }
 }
 
+// Notifier sends the end state of a bar to its MultiBar. A bar that
+// goes away without finishing is abandoned when its notifier is dropped,
+// so `listen` never waits for a bar that no longer exists.
+pub(crate) struct Notifier {
+    level: usize,
+    chan: Sender<WriteMsg>,
+    sent: bool,
+}
+
+impl Notifier {
+    pub(crate) fn finish(&mut self, state: EndState) {
+        let msg = match state {
+            EndState::Abandoned => WriteMsg::Abandon(self.level),
+            _ => WriteMsg::Finish(self.level, state),
+        };
+        self.send(msg);
+    }
+
+    fn send(&mut self, msg: WriteMsg) {
+        if !self.sent {
+            self.sent = true;
+            let _ = self.chan.send(msg);
+        }
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        self.send(WriteMsg::Abandon(self.level));
+    }
+}
+
 // WriteMsg is the message format used to communicate
 // between MultiBar and its bars
-struct WriteMsg {
-    done: bool,
-    level: usize,
-    string: String,
+enum WriteMsg {
+    // a new frame of the bar in `level` is pending.
+    Draw(usize),
+    // the bar in `level` finished in the given state.
+    Finish(usize, EndState),
+    // the bar in `level` was abandoned, or went away before it finished.
+    Abandon(usize),
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Layout, Output};
+    use crate::config::Config;
+    use crate::fixture::{Broken, Shared};
+    use crate::{MultiBar, ProgressBar, Stream, Style};
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn coalesce_frames() {
+        let out = Shared::default();
+        let mb = MultiBar::with_config(out.clone(), Config::default());
+        let mut pb = mb.create_bar(10);
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_width(Some(40));
+        for _ in 0..5 {
+            pb.inc();
+        }
+        pb.finish();
+        mb.listen();
+
+        let out = out.text();
+        assert_eq!(out.matches('\n').count(), 1, "should draw only once");
+        assert!(out.ends_with("\r\r10 / 10 [====================] 100.00 %\n"));
+    }
+
+    #[test]
+    fn finish_print_after_pause() {
+        let out = Shared::default();
+        let mb = MultiBar::with_config(out.clone(), Config::default());
+        let mut pb = mb.create_bar(10);
+        pb.add(3);
+        // past the default max refresh rate of the bar.
+        thread::sleep(Duration::from_millis(60));
+        pb.finish_print("done");
+        mb.listen();
+        assert_eq!(out.text().rsplit('\r').next(), Some("done\n"));
+    }
+
+    #[test]
+    fn draw_changed_lines() {
+        let mut layout = Layout {
+            lines: Vec::new(),
+            nlevels: 0,
+        };
+        let mut out = Output {
+            handle: Vec::new(),
+            drawn: Vec::new(),
+            tty: true,
+            console: Stream::Stdout,
+        };
+        layout.push("a".to_owned());
+        let b = layout.push("b".to_owned());
+        layout.push("c".to_owned());
+        out.draw(&layout).unwrap();
+        assert_eq!(out.handle, b"\x1B[2K\ra\n\x1B[2K\rb\n\x1B[2K\rc\n");
+
+        out.handle.clear();
+        out.draw(&layout).unwrap();
+        assert!(out.handle.is_empty(), "should not draw unchanged lines");
+
+        layout.lines[1].text = "B".to_owned();
+        out.draw(&layout).unwrap();
+        assert_eq!(out.handle, b"\x1B[2A\x1B[2K\rB\n\n");
+
+        out.handle.clear();
+        layout.set_hidden(b, true);
+        out.draw(&layout).unwrap();
+        assert_eq!(out.handle, b"\x1B[2A\x1B[2K\rc\n\x1B[2K\r");
+
+        // without a terminal, the rest of the last lines is padded.
+        let mut out = Output {
+            handle: Vec::new(),
+            drawn: vec!["long".to_owned(), "b".to_owned()],
+            tty: false,
+            console: Stream::Stdout,
+        };
+        out.draw(&layout).unwrap();
+        assert_eq!(out.handle, b"\x1B[2A\ra   \n\rc\n");
+        out.handle.clear();
+        out.clear().unwrap();
+        assert_eq!(out.handle, b"\x1B[2A\r \n\r \n\x1B[2A\r");
+    }
+
+    #[test]
+    fn abandon_on_drop() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let mut p1 = mb.create_bar(10);
+        let p2 = mb.create_bar(10);
+        p1.inc();
+        drop(p2);
+        p1.finish();
+        // should not block on the dropped bar.
+        mb.listen();
+    }
+
+    #[test]
+    fn broken_handle() {
+        let mb = MultiBar::with_config(Broken, Config::default());
+        let mut pb = mb.create_bar(10);
+        assert!(
+            mb.try_listen().is_err(),
+            "try_listen should return the error"
+        );
+        pb.finish();
+        // should not panic by default.
+        mb.listen();
+    }
+
+    #[test]
+    fn finish_print_broken_handle() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let mut pb = ProgressBar::with_config(Broken, 10, Config::default());
+        pb.notifier = mb.create_bar(10).notifier.take();
+        assert!(pb.try_finish_print("done").is_err());
+        // should not block on the bar that failed to draw.
+        mb.listen();
+        assert_eq!(mb.summary().succeeded, 1);
+    }
+
+    #[test]
+    fn dropped_multibar() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let mut pb = mb.create_bar(10);
+        drop(mb);
+        // should not panic when the MultiBar is gone.
+        pb.inc();
+        pb.finish();
+    }
+
+    #[test]
+    fn style() {
+        let mut mb = MultiBar::with_config(Vec::new(), Config::default());
+        mb.set_style(&Style::minimal()).unwrap();
+        assert!(mb.set_style(&Style::new().format("[]")).is_err());
+        let mut pb = mb.create_bar(10);
+        assert_eq!(pb.style(), &Style::minimal(), "should keep the valid style");
+        pb.finish();
+        mb.listen();
+    }
+
+    #[test]
+    fn summary() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let mut p1 = mb.create_bar(10);
+        let mut p2 = mb.create_bar(10);
+        let mut p3 = mb.create_bar(10);
+        let p4 = mb.create_bar(10);
+        p1.finish();
+        p2.finish_with_warning("");
+        p3.finish_with_error("");
+        drop(p4);
+        mb.listen();
+        assert_eq!(
+            mb.summary().to_string(),
+            "1 succeeded, 1 with warnings, 1 failed, 1 abandoned"
+        );
+    }
+
+    #[test]
+    fn suspend() {
+        let out = Shared::default();
+        let mb = Arc::new(MultiBar::with_config(out.clone(), Config::default()));
+        // outside of `listen`, `f` just runs.
+        mb.suspend(|| out.clone().write_all(b"before\n").unwrap());
+
+        let mut pb = mb.create_bar(10);
+        let listener = {
+            let mb = mb.clone();
+            thread::spawn(move || mb.listen())
+        };
+        while out.len() <= "before\n".len() {
+            thread::yield_now();
+        }
+        mb.suspend(|| out.clone().write_all(b"log line\n").unwrap());
+        pb.finish();
+        listener.join().unwrap();
+
+        let out = out.text();
+        let cleared = " \n\x1b[1A\rlog line\n\r\r0 / 10";
+        assert!(out.starts_with("before\n"));
+        assert!(out.contains(cleared), "should clear and redraw: {:?}", out);
+    }
+
+    #[test]
+    fn max_refresh_rate() {
+        let out = Shared::default();
+        let mut mb = MultiBar::with_config(out.clone(), Config::default());
+        mb.set_max_refresh_rate(Some(Duration::from_secs(60)));
+        let mb = Arc::new(mb);
+        let mut pb = mb.create_bar(10);
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        let listener = {
+            let mb = mb.clone();
+            thread::spawn(move || mb.listen())
+        };
+        while out.len() == 0 {
+            thread::yield_now();
+        }
+        for _ in 0..3 {
+            pb.inc();
+            thread::sleep(Duration::from_millis(10));
+        }
+        pb.finish();
+        listener.join().unwrap();
+
+        let out = out.text();
+        assert!(!out.contains("1 / 10"), "should wait for the next frame");
+        assert!(
+            out.contains("10 / 10"),
+            "should draw the end state right away"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let out = Shared::default();
+        let mut mb = MultiBar::with_config(out.clone(), Config::default());
+        mb.set_json(true);
+        mb.println("header");
+        let mut p1 = mb.create_bar(10);
+        let mut p2 = mb.create_bar(10);
+        p1.inc();
+        p2.abandon();
+        p1.finish();
+        mb.listen();
+
+        let out = out.text();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2, "should write the newest frames: {:?}", out);
+        assert!(lines[0].starts_with(r#"{"id":1,"pos":10,"#));
+        assert!(lines[0].ends_with(r#""state":"success"}"#));
+        assert!(lines[1].starts_with(r#"{"id":2,"pos":0,"#));
+        assert!(lines[1].ends_with(r#""state":"abandoned"}"#));
+    }
+
+    #[test]
+    fn env_disable_json() {
+        let out = Shared::default();
+        let config = Config {
+            hidden: true,
+            ..Config::default()
+        };
+        let mut mb = MultiBar::with_config(out.clone(), config);
+        mb.set_json(true);
+        let mut pb = mb.create_bar(10);
+        pb.add(4);
+        pb.finish();
+        mb.listen();
+        assert!(out.text().ends_with("\"state\":\"success\"}\n"));
+    }
+
+    #[test]
+    fn env_force_tty() {
+        let config = Config {
+            force_tty: true,
+            ..Config::default()
+        };
+        let mut mb = MultiBar::with_config(Vec::new(), config);
+        mb.set_stream(None);
+        assert!(mb.out.lock().unwrap().tty, "should draw as on a terminal");
+    }
 }
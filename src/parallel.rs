@@ -0,0 +1,207 @@
+use crate::ProgressBar;
+use rayon::iter::plumbing::{Consumer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use std::io::{Stdout, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// minimal time between two drawings of a bar from the worker threads.
+const DRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// ProgressParIter wraps a parallel iterator and increments a `ProgressBar`
+/// for every item, see `ParallelProgressIterator`.
+///
+/// The bar is finished when the iterator completes.
+pub struct ProgressParIter<T: Write, I> {
+    it: I,
+    shared: Arc<Shared<T>>,
+}
+
+// Shared is the state shared by the worker threads. Items are counted
+// with an atomic counter, and the bar is drawn by at most one worker at
+// a time, the others skip the drawing instead of waiting for the lock.
+struct Shared<T: Write> {
+    pb: Mutex<ProgressBar<T>>,
+    count: AtomicU64,
+    start: Instant,
+    // nanoseconds since `start` before which the bar is not drawn.
+    next_draw: AtomicU64,
+}
+
+impl<T: Write> Shared<T> {
+    fn inc(&self) {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let now = self.start.elapsed().as_nanos() as u64;
+        if now < self.next_draw.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Ok(mut pb) = self.pb.try_lock() {
+            self.next_draw
+                .store(now + DRAW_INTERVAL.as_nanos() as u64, Ordering::Relaxed);
+            // another worker may have drawn a newer count already.
+            if count > pb.current {
+                pb.set(count);
+            }
+        }
+    }
+
+    fn finish(&self) {
+        let mut pb = self.pb.lock().unwrap();
+        pb.set(self.count.load(Ordering::SeqCst));
+        pb.finish();
+    }
+}
+
+/// ParallelProgressIterator adds progress bars to rayon parallel iterators.
+///
+/// Available with the `rayon` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::ParallelProgressIterator;
+/// use rayon::prelude::*;
+///
+/// let v: Vec<u64> = (0..1_000_000).collect();
+/// let sum: u64 = v.par_iter().progress().map(|i| i * 2).sum();
+/// ```
+pub trait ParallelProgressIterator: ParallelIterator {
+    /// Wrap the iterator with a new `ProgressBar` on stdout. The total is
+    /// the length of the iterator, if it is known.
+    fn progress(self) -> ProgressParIter<Stdout, Self> {
+        let total = self.opt_len().unwrap_or(0) as u64;
+        self.progress_count(total)
+    }
+
+    /// Wrap the iterator with a new `ProgressBar` on stdout with the
+    /// given total.
+    fn progress_count(self, total: u64) -> ProgressParIter<Stdout, Self> {
+        self.progress_with(ProgressBar::new(total))
+    }
+
+    /// Wrap the iterator with the given `ProgressBar`.
+    ///
+    /// If the total of the bar is `0`, it is set to the length of the
+    /// iterator, if it is known.
+    fn progress_with<T: Write + Send>(self, mut pb: ProgressBar<T>) -> ProgressParIter<T, Self> {
+        if pb.total == 0 {
+            pb.total = self.opt_len().unwrap_or(0) as u64;
+        }
+        ProgressParIter {
+            it: self,
+            shared: Arc::new(Shared {
+                pb: Mutex::new(pb),
+                count: AtomicU64::new(0),
+                start: Instant::now(),
+                next_draw: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl<I: ParallelIterator> ParallelProgressIterator for I {}
+
+impl<T, I> ParallelIterator for ProgressParIter<T, I>
+where
+    T: Write + Send,
+    I: ParallelIterator,
+{
+    type Item = I::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let shared = self.shared.clone();
+        let r = self
+            .it
+            .map(move |item| {
+                shared.inc();
+                item
+            })
+            .drive_unindexed(consumer);
+        self.shared.finish();
+        r
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.it.opt_len()
+    }
+}
+
+impl<T, I> IndexedParallelIterator for ProgressParIter<T, I>
+where
+    T: Write + Send,
+    I: IndexedParallelIterator,
+{
+    fn len(&self) -> usize {
+        self.it.len()
+    }
+
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        let shared = self.shared.clone();
+        let r = self
+            .it
+            .map(move |item| {
+                shared.inc();
+                item
+            })
+            .drive(consumer);
+        self.shared.finish();
+        r
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        let shared = self.shared.clone();
+        let r = self
+            .it
+            .map(move |item| {
+                shared.inc();
+                item
+            })
+            .with_producer(callback);
+        self.shared.finish();
+        r
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::{ParallelProgressIterator, ProgressBar};
+    use rayon::prelude::*;
+
+    #[test]
+    fn sum() {
+        let v: Vec<u64> = (0..10_000).collect();
+        let it =
+            v.par_iter()
+                .progress_with(ProgressBar::with_config(Vec::new(), 0, Config::default()));
+        let shared = it.shared.clone();
+        assert_eq!(it.map(|i| i * 2).sum::<u64>(), 99_990_000);
+
+        let pb = shared.pb.lock().unwrap();
+        assert_eq!(pb.total, 10_000, "should use the iterator length");
+        assert_eq!(pb.current, 10_000, "should count every item");
+        assert!(pb.is_finish);
+    }
+
+    #[test]
+    fn indexed() {
+        let it = (0..1000)
+            .into_par_iter()
+            .progress_with(ProgressBar::with_config(Vec::new(), 0, Config::default()));
+        let shared = it.shared.clone();
+        let v: Vec<i32> = it.rev().collect();
+        assert_eq!(v.len(), 1000);
+        assert_eq!(v[0], 999);
+        assert_eq!(shared.pb.lock().unwrap().current, 1000);
+    }
+}
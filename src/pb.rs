@@ -1,4 +1,11 @@
-use crate::tty::{terminal_size, Width};
+use crate::clock;
+use crate::config::Config;
+use crate::json;
+use crate::multi::Notifier;
+use crate::spinner::frame_index;
+use crate::style::{chars, visible_width, ConfigError, Style};
+use crate::title::Title;
+use crate::tty::{clear_line, is_terminal, taskbar, CursorGuard, Stream, Taskbar, TermSize, Width};
 use std::io::Stdout;
 use std::io::{self, Write};
 use std::time::{Duration, Instant};
@@ -16,42 +23,150 @@ macro_rules! kb_fmt {
     }};
 }
 
-const FORMAT: &str = "[=>-]";
-const TICK_FORMAT: &str = "\\|/-";
+// the default max refresh rate, so updates in a tight loop only add to
+// the counter, and the bar is drawn at most 20 times per second.
+const REFRESH_RATE: Duration = Duration::from_millis(50);
 
 // Output type format, indicate which format wil be used in
 // the speed box.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Units {
     Default,
     Bytes,
 }
 
+/// What to do when writing to the handle of a bar fails, e.g. when the
+/// output is a closed pipe (`tool | head`).
+///
+/// The `try_` methods of `ProgressBar` (and `MultiBar::try_listen`) return
+/// the error instead, regardless of the policy.
+///
+/// The default is `Disable`, so a closed output does not bring the program
+/// down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Panic with the error.
+    Panic,
+    /// Ignore the error and keep drawing.
+    Ignore,
+    /// Stop drawing after the first error. This is the default.
+    Disable,
+}
+
+/// The state a bar ended in.
+///
+/// Bars that end with `finish_with_error`, `finish_with_warning`,
+/// `finish_at_current` or `abandon` are drawn with the glyph of their state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndState {
+    /// Finished successfully, drawn with `✔`.
+    Success,
+    /// Finished with a warning, drawn with `⚠`.
+    Warning,
+    /// Finished with an error, drawn with `✖`.
+    Error,
+    /// Abandoned before it finished, drawn with `✖`.
+    Abandoned,
+}
+
+impl EndState {
+    fn glyph(self) -> &'static str {
+        match self {
+            EndState::Success => "✔",
+            EndState::Warning => "⚠",
+            EndState::Error | EndState::Abandoned => "✖",
+        }
+    }
+
+    // name of the state in the JSON output.
+    fn name(self) -> &'static str {
+        match self {
+            EndState::Success => "success",
+            EndState::Warning => "warning",
+            EndState::Error => "error",
+            EndState::Abandoned => "abandoned",
+        }
+    }
+
+    // ANSI escape code of the glyph color.
+    fn color(self) -> &'static str {
+        match self {
+            EndState::Success => "\x1B[32m",
+            EndState::Warning => "\x1B[33m",
+            EndState::Error | EndState::Abandoned => "\x1B[31m",
+        }
+    }
+}
+
+impl ErrorPolicy {
+    // apply the policy to `e`, and return whether drawing should be disabled.
+    pub(crate) fn handle(self, e: io::Error) -> bool {
+        match self {
+            ErrorPolicy::Panic => panic!("write() fail: {}", e),
+            ErrorPolicy::Ignore => false,
+            ErrorPolicy::Disable => true,
+        }
+    }
+}
+
+// getters and setters of the `show_*` flags of the style of a bar.
+macro_rules! show_boxes {
+    ($($(#[$doc:meta])* $name:ident, $set:ident;)*) => {$(
+        $(#[$doc])*
+        pub fn $name(&self) -> bool {
+            self.style.$name
+        }
+
+        #[doc = concat!("Set `", stringify!($name), "`.")]
+        pub fn $set(&mut self, show: bool) {
+            self.style.$name = show;
+        }
+    )*};
+}
+
 pub struct ProgressBar<T: Write> {
     start_time: Instant,
-    units: Units,
-    pub total: u64,
-    current: u64,
-    bar_start: String,
-    bar_current: String,
-    bar_current_n: String,
-    bar_remain: String,
-    bar_end: String,
-    tick: Vec<String>,
+    pub(crate) total: u64,
+    pub(crate) current: u64,
+    pub(crate) style: Style,
     tick_state: usize,
     width: Option<usize>,
+    term_size: TermSize,
+    stream: Option<Stream>,
+    // whether the handle is a terminal, so the line of the bar can be
+    // erased instead of padded with spaces.
+    tty: bool,
     message: String,
     last_refresh_time: Instant,
     max_refresh_rate: Option<Duration>,
-    pub is_finish: bool,
-    pub is_multibar: bool,
-    pub show_bar: bool,
-    pub show_speed: bool,
-    pub show_percent: bool,
-    pub show_counter: bool,
-    pub show_time_left: bool,
-    pub show_tick: bool,
-    pub show_message: bool,
+    // the tick of `clock` in which the clock was read last, the updates
+    // within the same tick are skipped without reading it.
+    checked: Option<u64>,
+    error_policy: ErrorPolicy,
+    disabled: bool,
+    // whether drawing is disabled by the environment, see `Config`. Unlike
+    // `disabled`, the text of `finish_print` and the JSON lines are still
+    // written.
+    hidden: bool,
+    // whether to draw as on a terminal, even if the handle is not one.
+    force_tty: bool,
+    end: Option<EndState>,
+    // whether to draw the glyph of `end`.
+    marked: bool,
+    color: bool,
+    json: bool,
+    taskbar: bool,
+    // the last state reported with `OSC 9;4`.
+    taskbar_sent: Option<Taskbar>,
+    title: Option<Title>,
+    hide_cursor: bool,
+    // hides the cursor from the first drawing until the bar is done.
+    cursor: Option<CursorGuard>,
+    // id of the bar in the JSON output of a MultiBar.
+    pub(crate) id: Option<usize>,
+    pub(crate) is_finish: bool,
+    pub(crate) is_multibar: bool,
+    pub(crate) notifier: Option<Notifier>,
     handle: T,
 }
 
@@ -75,7 +190,9 @@ impl ProgressBar<Stdout> {
     /// ```
     pub fn new(total: u64) -> ProgressBar<Stdout> {
         let handle = ::std::io::stdout();
-        ProgressBar::on(handle, total)
+        let mut pb = ProgressBar::on(handle, total);
+        pb.set_stream(Some(Stream::Stdout));
+        pb
     }
 }
 
@@ -83,6 +200,9 @@ impl<T: Write> ProgressBar<T> {
     /// Create a new ProgressBar with default configuration but
     /// pass an arbitrary writer.
     ///
+    /// The bar is drawn as plain text, unless the handle writes to a
+    /// standard stream that is set with `set_stream`.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -100,35 +220,51 @@ impl<T: Write> ProgressBar<T> {
     /// }
     /// ```
     pub fn on(handle: T, total: u64) -> ProgressBar<T> {
+        ProgressBar::with_config(handle, total, Config::from_env())
+    }
+
+    // create a bar with the defaults of `config` instead of the ones of
+    // the environment.
+    pub(crate) fn with_config(handle: T, total: u64, config: Config) -> ProgressBar<T> {
         let mut pb = ProgressBar {
             total,
             current: 0,
             start_time: Instant::now(),
-            units: Units::Default,
             is_finish: false,
             is_multibar: false,
-            show_bar: true,
-            show_speed: true,
-            show_percent: true,
-            show_counter: true,
-            show_time_left: true,
-            show_tick: false,
-            show_message: true,
-            bar_start: String::new(),
-            bar_current: String::new(),
-            bar_current_n: String::new(),
-            bar_remain: String::new(),
-            bar_end: String::new(),
-            tick: Vec::new(),
+            style: Style::new(),
             tick_state: 0,
             width: None,
+            term_size: TermSize::new(None),
+            stream: None,
+            tty: false,
             message: String::new(),
             last_refresh_time: Instant::now(),
             max_refresh_rate: None,
+            checked: None,
+            error_policy: ErrorPolicy::Disable,
+            disabled: false,
+            hidden: false,
+            force_tty: false,
+            end: None,
+            marked: false,
+            color: true,
+            json: false,
+            taskbar: false,
+            taskbar_sent: None,
+            title: None,
+            hide_cursor: false,
+            cursor: None,
+            id: None,
+            notifier: None,
             handle,
         };
-        pb.format(FORMAT);
-        pb.tick_format(TICK_FORMAT);
+        pb.hidden = config.hidden;
+        pb.force_tty = config.force_tty;
+        pb.tty = config.force_tty;
+        pb.width = config.width;
+        pb.color = config.color;
+        pb.set_max_refresh_rate(Some(config.refresh_rate.unwrap_or(REFRESH_RATE)));
         pb
     }
 
@@ -144,7 +280,7 @@ impl<T: Write> ProgressBar<T> {
     /// pb.set_units(Units::Bytes);
     /// ```
     pub fn set_units(&mut self, u: Units) {
-        self.units = u;
+        self.style.units = u;
     }
 
     /// Set custom format to the drawing bar, default is `[=>-]`
@@ -156,13 +292,10 @@ impl<T: Write> ProgressBar<T> {
     /// pb.format("[=>_]");
     /// ```
     pub fn format(&mut self, fmt: &str) {
-        if fmt.len() >= 5 {
-            let v: Vec<&str> = fmt.split("").collect();
-            self.bar_start = v[1].to_owned();
-            self.bar_current = v[2].to_owned();
-            self.bar_current_n = v[3].to_owned();
-            self.bar_remain = v[4].to_owned();
-            self.bar_end = v[5].to_owned();
+        let mut bar = chars(fmt);
+        if bar.len() >= 5 {
+            bar.truncate(5);
+            self.style.bar = bar;
         }
     }
 
@@ -194,22 +327,86 @@ impl<T: Write> ProgressBar<T> {
     /// Format is not limited to 4 characters, any string can
     /// be used as a tick format (the tick will successively
     /// take the value of each char but won't loop backwards).
+    /// It does not show the tick box, see `set_show_tick`.
     ///
     ///
     /// # Examples
     /// ```ignore
     /// let mut pb = ProgressBar::new(...);
-    /// pb.tick_format("▀▐▄▌")
+    /// pb.tick_format("▀▐▄▌");
+    /// pb.set_show_tick(true);
     /// ```
     pub fn tick_format(&mut self, tick_fmt: &str) {
-        if tick_fmt != TICK_FORMAT {
-            self.show_tick = true;
+        if !tick_fmt.is_empty() {
+            self.style.tick = chars(tick_fmt);
+            self.style.tick_interval = None;
+            self.tick_state = 0;
         }
-        self.tick = tick_fmt
-            .split("")
-            .map(|x| x.to_owned())
-            .filter(|x| !x.is_empty())
-            .collect();
+    }
+
+    /// The style of the bar.
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+
+    /// Set the style of the bar, or return why it can not be drawn.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::{ProgressBar, Style};
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_style(&Style::new().format("|#--|").show_speed(false))
+    ///     .unwrap();
+    /// ```
+    pub fn set_style(&mut self, style: &Style) -> Result<(), ConfigError> {
+        style.validate()?;
+        self.style = style.clone();
+        self.tick_state = 0;
+        Ok(())
+    }
+
+    show_boxes! {
+        /// Whether the bar is shown.
+        show_bar, set_show_bar;
+        /// Whether the speed box is shown.
+        show_speed, set_show_speed;
+        /// Whether the percent box is shown.
+        show_percent, set_show_percent;
+        /// Whether the counter box is shown.
+        show_counter, set_show_counter;
+        /// Whether the time left box is shown.
+        show_time_left, set_show_time_left;
+        /// Whether the tick box is shown.
+        show_tick, set_show_tick;
+        /// Whether the message box is shown.
+        show_message, set_show_message;
+    }
+
+    /// The total of the bar.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Set the total of the bar, e.g. once it is known.
+    pub fn set_total(&mut self, total: u64) {
+        self.total = total;
+    }
+
+    /// The current value of the bar.
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Whether the bar is finished.
+    pub fn is_finish(&self) -> bool {
+        self.is_finish
+    }
+
+    /// Whether the bar is drawn by a `MultiBar`.
+    pub fn is_multibar(&self) -> bool {
+        self.is_multibar
     }
 
     /// Set width, or `None` for default.
@@ -224,8 +421,41 @@ impl<T: Write> ProgressBar<T> {
         self.width = w;
     }
 
+    /// Set the standard stream the handle writes to, or `None` if it is
+    /// not one, default is `Stdout` for `new` and `None` for `on`.
+    ///
+    /// If the stream is a terminal, the line of the bar is erased before
+    /// each drawing. Otherwise it is padded with spaces instead, so files,
+    /// pipes and CI logs get no escape sequences. Without a stream, the bar
+    /// is as wide as the terminal on stdout, or else on stderr.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::{ProgressBar, Stream};
+    /// use std::io::stderr;
+    ///
+    /// let mut pb = ProgressBar::on(stderr(), 10);
+    /// pb.set_stream(Some(Stream::Stderr));
+    /// ```
+    pub fn set_stream(&mut self, stream: Option<Stream>) {
+        self.stream = stream;
+        self.tty = self.force_tty || matches!(stream, Some(s) if is_terminal(s));
+        self.term_size = TermSize::new(stream);
+        // the title goes to the new stream, if it is still a terminal.
+        if let Some(title) = self.title.take() {
+            self.set_title(Some(title.label()));
+        }
+    }
+
     /// Set max refresh rate, above which the progress bar will not redraw, or `None` for none.
     ///
+    /// The default is 50ms. Updates within it only
+    /// add to the counter, without reading the size of the terminal or
+    /// writing to the handle, and in a tight loop most of them don't read
+    /// the clock either. The bar is always drawn when it reaches its
+    /// total, or finishes.
+    ///
     /// # Examples
     ///
     /// ```ignore
@@ -234,11 +464,134 @@ impl<T: Write> ProgressBar<T> {
     /// ```
     pub fn set_max_refresh_rate(&mut self, w: Option<Duration>) {
         self.max_refresh_rate = w;
-        if let Some(dur) = self.max_refresh_rate {
-            self.last_refresh_time = self.last_refresh_time - dur;
+        self.checked = None;
+        // let the next update draw right away.
+        if let Some(t) = w.and_then(|dur| self.last_refresh_time.checked_sub(dur)) {
+            self.last_refresh_time = t;
+        }
+    }
+
+    /// Set what to do when writing to the handle fails, default is
+    /// `ErrorPolicy::Disable`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::{ErrorPolicy, ProgressBar};
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_error_policy(ErrorPolicy::Ignore);
+    /// ```
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    /// Set whether to color the glyph of the end state, default is `true`.
+    ///
+    /// The glyph is only colored on a terminal, see `set_stream`.
+    pub fn set_color(&mut self, color: bool) {
+        self.color = color;
+    }
+
+    /// Set whether to draw the bar as JSON lines instead of text, default
+    /// is `false`.
+    ///
+    /// Each drawing writes a line like
+    /// `{"pos":42,"total":100,"rate":12.50,"eta_secs":5,"msg":"copy","state":"running"}`,
+    /// where `eta_secs` is `null` while unknown, and the last line has the
+    /// end state of the bar (`success`, `warning`, `error` or `abandoned`).
+    /// Use it when the output is read by another program.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_json(true);
+    /// pb.inc();
+    /// pb.finish();
+    /// ```
+    pub fn set_json(&mut self, json: bool) {
+        self.json = json;
+    }
+
+    /// Set whether to report the progress to the terminal with `OSC 9;4`,
+    /// default is `false`.
+    ///
+    /// Terminals that support it (e.g. Windows Terminal, ConEmu, WezTerm,
+    /// Ghostty) show the progress in the tab or the taskbar, so it is
+    /// visible while the window is in the background. The progress is
+    /// removed when the bar finishes successfully, and is left in the error
+    /// (or warning) color when it ends in another state. Bars of a
+    /// `MultiBar` and bars drawn as JSON do not report their progress.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_taskbar(true);
+    /// ```
+    pub fn set_taskbar(&mut self, taskbar: bool) {
+        self.taskbar = taskbar;
+    }
+
+    /// Show the progress in the title of the terminal window, like
+    /// `42% — backup` for the label `backup`, or `None` to stop, default
+    /// is `None`.
+    ///
+    /// The title is updated when the bar is drawn and the title changed,
+    /// so it is limited by the max refresh rate as well, and the previous
+    /// title is restored when the bar finishes or is dropped. The title is
+    /// only shown if the stream of the bar is a terminal (see
+    /// `set_stream`), and not for bars of a `MultiBar` or bars drawn as
+    /// JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_title(Some("backup"));
+    /// ```
+    pub fn set_title(&mut self, label: Option<&str>) {
+        // dropping the current title restores the previous one.
+        self.title = match (label, self.stream) {
+            (Some(label), Some(stream)) if self.tty => Some(Title::open(stream, label)),
+            _ => None,
+        };
+    }
+
+    /// Set whether to hide the cursor of the terminal while the bar is
+    /// drawn, default is `false`.
+    ///
+    /// It is only hidden when the bar is drawn on a terminal. The cursor
+    /// is shown again when the bar finishes or is dropped, and also if the
+    /// program panics before that, see `show_cursor_on_signal` for signals.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.set_hide_cursor(true);
+    /// ```
+    pub fn set_hide_cursor(&mut self, hide: bool) {
+        self.hide_cursor = hide;
+        if !hide {
+            self.cursor = None;
         }
     }
 
+    /// The state the bar ended in, or `None` if it did not finish yet.
+    pub fn end_state(&self) -> Option<EndState> {
+        self.end
+    }
+
     /// Update progress bar even though no progress are made
     /// Useful to see if a program is bricked or just
     /// not doing any progress.
@@ -258,10 +611,17 @@ impl<T: Write> ProgressBar<T> {
     /// pb.finish();
     /// ```
     pub fn tick(&mut self) {
-        self.tick_state = (self.tick_state + 1) % self.tick.len();
+        let r = self.try_tick();
+        self.check(r);
+    }
+
+    /// Like `tick`, but return the error of writing to the handle.
+    pub fn try_tick(&mut self) -> io::Result<()> {
+        self.tick_state = (self.tick_state + 1) % self.style.tick.len();
         if self.current <= self.total {
-            self.draw()
+            self.draw()?;
         }
+        Ok(())
     }
 
     /// Add to current value
@@ -276,11 +636,18 @@ impl<T: Write> ProgressBar<T> {
     /// pb.finish();
     /// ```
     pub fn add(&mut self, i: u64) -> u64 {
-        self.current += i;
-        self.tick();
+        let r = self.try_add(i);
+        self.check(r);
         self.current
     }
 
+    /// Like `add`, but return the error of writing to the handle.
+    pub fn try_add(&mut self, i: u64) -> io::Result<u64> {
+        self.current += i;
+        self.try_tick()?;
+        Ok(self.current)
+    }
+
     /// Manually set the current value of the bar
     ///
     /// # Examples
@@ -291,44 +658,126 @@ impl<T: Write> ProgressBar<T> {
     /// pb.set(8);
     /// pb.finish();
     pub fn set(&mut self, i: u64) -> u64 {
-        self.current = i;
-        self.tick();
+        let r = self.try_set(i);
+        self.check(r);
         self.current
     }
 
+    /// Like `set`, but return the error of writing to the handle.
+    pub fn try_set(&mut self, i: u64) -> io::Result<u64> {
+        self.current = i;
+        self.try_tick()?;
+        Ok(self.current)
+    }
+
     /// Increment current value
     pub fn inc(&mut self) -> u64 {
         self.add(1)
     }
 
+    /// Like `inc`, but return the error of writing to the handle.
+    pub fn try_inc(&mut self) -> io::Result<u64> {
+        self.try_add(1)
+    }
+
     /// Resets the start time to now
     pub fn reset_start_time(&mut self) {
         self.start_time = Instant::now();
     }
 
-    fn draw(&mut self) {
-        let now = Instant::now();
+    /// Hide the bar while `f` runs, and draw it again after.
+    ///
+    /// Use it to print to the terminal while the bar is active, without
+    /// leaving a half-drawn bar behind. A finished bar is left as is, and
+    /// bars of a `MultiBar` are hidden with `MultiBar::suspend`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.inc();
+    /// pb.suspend(|| println!("skipping a broken entry"));
+    /// pb.inc();
+    /// ```
+    pub fn suspend<F: FnOnce() -> R, R>(&mut self, f: F) -> R {
+        if self.is_multibar || self.is_finish || self.disabled || self.hidden || self.json {
+            return f();
+        }
+        let r = self.blank();
+        self.check(r);
+        let out = f();
+        // redraw right away, even within the max refresh rate.
+        let mrr = self.max_refresh_rate.take();
+        let r = self.draw();
+        self.max_refresh_rate = mrr;
+        self.check(r);
+        out
+    }
+
+    // check applies the error policy to the result of a `try_` method.
+    fn check<R>(&mut self, r: io::Result<R>) {
+        if let Err(e) = r {
+            if self.error_policy.handle(e) {
+                self.disabled = true;
+            }
+        }
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        // the environment only hides the drawing, not the JSON lines.
+        if self.disabled || (self.hidden && !self.json) {
+            return Ok(());
+        }
         if let Some(mrr) = self.max_refresh_rate {
-            if now - self.last_refresh_time < mrr && self.current < self.total {
-                return;
+            let done = self.total > 0 && self.current >= self.total;
+            if !done {
+                // the clock can not have passed the rate within the tick
+                // it was read in last, if the rate is longer than a tick.
+                if mrr > clock::RESOLUTION {
+                    let tick = clock::ticks();
+                    if tick.is_some() && tick == self.checked {
+                        return Ok(());
+                    }
+                    self.checked = tick;
+                }
+                if Instant::now() - self.last_refresh_time < mrr {
+                    return Ok(());
+                }
             }
         }
+        let now = Instant::now();
 
         let mut time_elapsed = now - self.start_time;
         if time_elapsed.is_zero() {
             time_elapsed = Duration::from_nanos(1);
         }
         let speed = self.current as f64 / time_elapsed.as_secs_f64();
+        if self.json {
+            return self.draw_json(speed);
+        }
+        let percent = self.percent();
+        if let (false, Some(title)) = (self.is_multibar, self.title.as_mut()) {
+            title.set(percent);
+        }
+        if self.hide_cursor
+            && self.tty
+            && self.cursor.is_none()
+            && !self.is_finish
+            && !self.is_multibar
+        {
+            self.cursor = self.stream.map(CursorGuard::hide);
+        }
         let width = self.width();
 
-        let mut out;
         let mut parts = Vec::new();
         let mut base = String::new();
         let mut prefix = String::new();
         let mut suffix = String::from(" ");
 
         // precent box
-        if self.show_percent {
+        if self.style.show_percent {
             let percent = self.current as f64 / (self.total as f64 / 100f64);
             parts.push(format!(
                 "{:.*} %",
@@ -337,14 +786,14 @@ impl<T: Write> ProgressBar<T> {
             ));
         }
         // speed box
-        if self.show_speed {
-            match self.units {
+        if self.style.show_speed {
+            match self.style.units {
                 Units::Default => parts.push(format!("{:.*}/s", 2, speed)),
                 Units::Bytes => parts.push(format!("{}/s", kb_fmt!(speed))),
             };
         }
         // time left box
-        if self.show_time_left && self.current > 0 && self.total > self.current {
+        if self.style.show_time_left && self.current > 0 && self.total > self.current {
             let left = 1. / speed * (self.total - self.current) as f64;
             if left < 60. {
                 parts.push(format!("{:.0}s", left));
@@ -354,59 +803,183 @@ impl<T: Write> ProgressBar<T> {
         }
         suffix += &parts.join(" ");
         // message box
-        if self.show_message {
+        if self.style.show_message {
             prefix = prefix + &self.message;
         }
         // counter box
-        if self.show_counter {
+        if self.style.show_counter {
             let (c, t) = (self.current as f64, self.total as f64);
             prefix = prefix
-                + &match self.units {
+                + &match self.style.units {
                     Units::Default => format!("{} / {} ", c, t),
                     Units::Bytes => format!("{} / {} ", kb_fmt!(c), kb_fmt!(t)),
                 };
         }
         // tick box
-        if self.show_tick {
-            prefix = prefix + &format!("{} ", self.tick[self.tick_state]);
+        if self.style.show_tick {
+            let tick = match self.style.tick_interval {
+                // a timed spinner turns with the time since the start.
+                Some(interval) => frame_index(time_elapsed, interval, self.style.tick.len()),
+                None => self.tick_state,
+            };
+            prefix = prefix + &format!("{} ", self.style.tick[tick]);
+        }
+        // end state box
+        let mut marker = String::new();
+        let mut marker_width = 0;
+        if let (true, Some(state)) = (self.marked, self.end) {
+            marker = if self.color && self.tty {
+                format!("{}{}\x1B[0m ", state.color(), state.glyph())
+            } else {
+                format!("{} ", state.glyph())
+            };
+            marker_width = 2;
         }
         // bar box
-        if self.show_bar {
-            let p = prefix.chars().count() + suffix.chars().count() + 3;
+        if self.style.show_bar {
+            let p = marker_width + visible_width(&prefix) + visible_width(&suffix) + 3;
             if p < width {
                 let size = width - p;
                 let curr_count =
                     ((self.current as f64 / self.total as f64) * size as f64).ceil() as usize;
                 if size >= curr_count {
                     let rema_count = size - curr_count;
-                    base = self.bar_start.clone();
+                    let bar = &self.style.bar;
+                    base = bar[0].clone();
                     if rema_count > 0 && curr_count > 0 {
-                        base =
-                            base + &self.bar_current.repeat(curr_count - 1) + &self.bar_current_n;
+                        base = base + &bar[1].repeat(curr_count - 1) + &bar[2];
                     } else {
-                        base = base + &self.bar_current.repeat(curr_count);
+                        base = base + &bar[1].repeat(curr_count);
                     }
-                    base = base + &self.bar_remain.repeat(rema_count) + &self.bar_end;
+                    base = base + &bar[3].repeat(rema_count) + &bar[4];
                 }
             }
         }
-        out = prefix + &base + &suffix;
-        // pad
-        if out.len() < width {
-            let gap = width - out.len();
-            out = out + &" ".repeat(gap);
-        }
+        let out = marker + &prefix + &base + &suffix;
         // print
-        printfl!(self.handle, "\r{}", out);
+        let osc = self.taskbar_state();
+        self.print_line(osc, &out)?;
+
+        self.last_refresh_time = Instant::now();
+        Ok(())
+    }
+
+    // write `line` over the line of the bar, after `pre`.
+    fn print_line(&mut self, pre: String, line: &str) -> io::Result<()> {
+        let mut out = pre;
+        self.clear_line(&mut out)?;
+        out += &self.padded(line);
+        printfl!(self.handle, "{}", out)
+    }
+
+    // clear the line of the bar, and leave the cursor at its start.
+    fn blank(&mut self) -> io::Result<()> {
+        let mut out = String::new();
+        self.clear_line(&mut out)?;
+        if !self.tty {
+            out += &" ".repeat(self.width());
+            out += "\r";
+        }
+        printfl!(self.handle, "{}", out)
+    }
+
+    // append the erasing of the line of the bar to `out`, on a terminal.
+    // The lines of a MultiBar are cleared by the MultiBar. On windows the
+    // line is cleared right away, so what is in `out` is written first.
+    fn clear_line(&mut self, out: &mut String) -> io::Result<()> {
+        if self.tty && !self.is_multibar {
+            if cfg!(windows) && !out.is_empty() {
+                printfl!(self.handle, "{}", out)?;
+                out.clear();
+            }
+            *out += &clear_line(self.stream.unwrap_or(Stream::Stdout));
+        }
+        *out += "\r";
+        Ok(())
+    }
+
+    // `s` padded with spaces to the width of the bar, so it covers the
+    // last drawing where the line is not erased.
+    fn padded(&mut self, s: &str) -> String {
+        let mut out = s.to_owned();
+        if !self.tty && !self.is_multibar {
+            let (len, width) = (visible_width(s), self.width());
+            if len < width {
+                out += &" ".repeat(width - len);
+            }
+        }
+        out
+    }
+
+    // the progress in whole percent, if the total is known.
+    fn percent(&self) -> Option<u64> {
+        match self.total {
+            0 => None,
+            t => Some(self.current.min(t) * 100 / t),
+        }
+    }
+
+    // the OSC 9;4 sequence of the current state, if the bar reports it
+    // and the state changed since it was last reported.
+    fn taskbar_state(&mut self) -> String {
+        if !self.taskbar || self.is_multibar {
+            return String::new();
+        }
+        let percent = self.percent().unwrap_or(0) as u8;
+        let state = match self.end {
+            None if self.total == 0 => Taskbar::Indeterminate,
+            None => Taskbar::Normal(percent),
+            Some(EndState::Success) => Taskbar::Clear,
+            Some(EndState::Warning) => Taskbar::Warning(percent),
+            Some(EndState::Error) | Some(EndState::Abandoned) => Taskbar::Error(percent),
+        };
+        if self.taskbar_sent == Some(state) {
+            return String::new();
+        }
+        self.taskbar_sent = Some(state);
+        taskbar(state)
+    }
+
+    fn draw_json(&mut self, speed: f64) -> io::Result<()> {
+        let eta_secs = if self.current >= self.total {
+            Some(0.)
+        } else if self.current > 0 {
+            Some((self.total - self.current) as f64 / speed)
+        } else {
+            None
+        };
+        let line = json::Line {
+            id: self.id,
+            pos: self.current,
+            total: self.total,
+            rate: speed,
+            eta_secs,
+            msg: self.message.trim(),
+            state: self.end.map_or("running", EndState::name),
+        };
+        printfl!(self.handle, "{}\n", line)?;
 
         self.last_refresh_time = Instant::now();
+        Ok(())
     }
 
     // finish_draw ensure that the progress bar is reached to its end, and do the
     // last drawing if needed.
-    fn finish_draw(&mut self) {
+    fn finish_draw(&mut self) -> io::Result<()> {
+        // a bar that ended at its current position stays there, and one
+        // that finished is not drawn over what was written after.
+        if self.marked || self.is_finish {
+            return Ok(());
+        }
         let mut redraw = false;
 
+        // the JSON output always ends with a line in the end state.
+        if self.json {
+            self.end.get_or_insert(EndState::Success);
+            self.max_refresh_rate = None;
+            redraw = true;
+        }
+
         if let Some(mrr) = self.max_refresh_rate {
             if Instant::now() - self.last_refresh_time < mrr {
                 self.max_refresh_rate = None;
@@ -419,29 +992,77 @@ impl<T: Write> ProgressBar<T> {
             redraw = true;
         }
 
-        if redraw {
-            self.draw();
-        }
+        let r = if redraw { self.draw() } else { Ok(()) };
         self.is_finish = true;
+        self.end.get_or_insert(EndState::Success);
+        self.release();
+        r
+    }
+
+    // finish a bar that is not drawn, and write `s` as a line of plain text.
+    fn finish_plain(&mut self, s: &str) -> io::Result<()> {
+        self.finish_draw()?;
+        if !self.disabled {
+            printfl!(self.handle, "{}\n", s)?;
+        }
+        self.try_finish()
+    }
+
+    // restore the title and the cursor, once the bar is done.
+    fn release(&mut self) {
+        if let Some(title) = self.title.as_mut() {
+            title.restore();
+        }
+        self.cursor = None;
     }
 
     /// Calling finish manually will set current to total and draw
     /// the last time
     pub fn finish(&mut self) {
-        self.finish_draw();
-        self.handle.write(b"").expect("write() failed");
+        let r = self.try_finish();
+        self.check(r);
+    }
+
+    /// Like `finish`, but return the error of writing to the handle.
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        let mut r = self.finish_draw();
+        // the last drawing may be skipped, the progress is removed anyway.
+        if r.is_ok() && !self.disabled && !self.hidden && !self.json {
+            let osc = self.taskbar_state();
+            if !osc.is_empty() {
+                r = printfl!(self.handle, "{}", osc);
+            }
+        }
+        if let (Some(n), Some(state)) = (self.notifier.as_mut(), self.end) {
+            n.finish(state);
+        }
+        r
     }
 
     /// Call finish and write string `s` that will replace the progress bar.
     pub fn finish_print(&mut self, s: &str) {
-        self.finish_draw();
-        let width = self.width();
-        let mut out = s.to_owned();
-        if s.len() < width {
-            out += &" ".repeat(width - s.len());
-        };
-        printfl!(self.handle, "\r{}", out);
-        self.finish();
+        let r = self.try_finish_print(s);
+        self.check(r);
+    }
+
+    /// Like `finish_print`, but return the error of writing to the handle.
+    pub fn try_finish_print(&mut self, s: &str) -> io::Result<()> {
+        // in JSON, `s` is the message of the last line.
+        if self.json {
+            self.message(s);
+            return self.try_finish();
+        }
+        if self.hidden {
+            return self.finish_plain(s);
+        }
+        let mut r = self.finish_draw();
+        if r.is_ok() && !self.disabled {
+            r = self.print_line(String::new(), s);
+        }
+        // notify the MultiBar even if the drawing failed, so it does not
+        // wait for the bar.
+        let f = self.try_finish();
+        r.and(f)
     }
 
     /// Call finish and write string `s` below the progress bar.
@@ -449,20 +1070,138 @@ impl<T: Write> ProgressBar<T> {
     /// If the ProgressBar is part of MultiBar instance, you should use
     /// `finish_print` to print message.
     pub fn finish_println(&mut self, s: &str) {
+        let r = self.try_finish_println(s);
+        self.check(r);
+    }
+
+    /// Like `finish_println`, but return the error of writing to the handle.
+    pub fn try_finish_println(&mut self, s: &str) -> io::Result<()> {
         // `finish_println` does not allow in MultiBar mode, because printing
-        // new line will break the multiBar output.
-        if self.is_multibar {
-            return self.finish_print(s);
+        // new line will break the multiBar output, and neither in JSON.
+        if self.is_multibar || self.json || self.hidden {
+            return self.try_finish_print(s);
+        }
+        self.finish_draw()?;
+        if !self.disabled {
+            let osc = self.taskbar_state();
+            printfl!(self.handle, "{}\n{}", osc, s)?;
         }
-        self.finish_draw();
-        printfl!(self.handle, "\n{}", s);
+        Ok(())
+    }
+
+    /// Finish the bar at its current position, without setting current
+    /// to total, and draw it the last time with a `✔`.
+    pub fn finish_at_current(&mut self) {
+        let r = self.try_finish_at_current();
+        self.check(r);
+    }
+
+    /// Like `finish_at_current`, but return the error of writing to the handle.
+    pub fn try_finish_at_current(&mut self) -> io::Result<()> {
+        self.try_end(EndState::Success)
+    }
+
+    /// Set the message of the bar, and finish it at its current position
+    /// with a `⚠`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.add(10);
+    /// pb.finish_with_warning("2 files skipped ");
+    /// ```
+    pub fn finish_with_warning(&mut self, message: &str) {
+        let r = self.try_finish_with_warning(message);
+        self.check(r);
+    }
+
+    /// Like `finish_with_warning`, but return the error of writing to the handle.
+    pub fn try_finish_with_warning(&mut self, message: &str) -> io::Result<()> {
+        self.message(message);
+        self.try_end(EndState::Warning)
+    }
+
+    /// Set the message of the bar, and finish it at its current position
+    /// with a `✖`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.add(4);
+    /// pb.finish_with_error("connection reset ");
+    /// ```
+    pub fn finish_with_error(&mut self, message: &str) {
+        let r = self.try_finish_with_error(message);
+        self.check(r);
+    }
+
+    /// Like `finish_with_error`, but return the error of writing to the handle.
+    pub fn try_finish_with_error(&mut self, message: &str) -> io::Result<()> {
+        self.message(message);
+        self.try_end(EndState::Error)
+    }
+
+    /// Stop the bar at its current position, and draw it the last time
+    /// with a failure marker.
+    ///
+    /// If the ProgressBar is part of MultiBar instance, the `MultiBar` is
+    /// notified that the bar is done. Bars that are dropped before they
+    /// finished are abandoned as well, but are not drawn again.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::ProgressBar;
+    ///
+    /// let mut pb = ProgressBar::new(10);
+    /// pb.add(3);
+    /// pb.abandon();
+    /// ```
+    pub fn abandon(&mut self) {
+        let r = self.try_abandon();
+        self.check(r);
+    }
+
+    /// Like `abandon`, but return the error of writing to the handle.
+    pub fn try_abandon(&mut self) -> io::Result<()> {
+        self.try_end(EndState::Abandoned)
+    }
+
+    /// Set the message of the bar and `abandon` it.
+    pub fn abandon_with_message(&mut self, message: &str) {
+        self.message(message);
+        self.abandon();
+    }
+
+    // try_end ends the bar in `state` at its current position, and draw it
+    // the last time with the glyph of `state`.
+    fn try_end(&mut self, state: EndState) -> io::Result<()> {
+        if self.is_finish {
+            return Ok(());
+        }
+        self.end = Some(state);
+        self.marked = true;
+        self.max_refresh_rate = None;
+        let r = self.draw();
+        self.is_finish = true;
+        self.release();
+        if let Some(n) = self.notifier.as_mut() {
+            n.finish(state);
+        }
+        r
     }
 
     /// Get terminal width, from configuration, terminal size, or default(80)
     fn width(&mut self) -> usize {
         if let Some(w) = self.width {
             w
-        } else if let Some((Width(w), _)) = terminal_size() {
+        } else if let Some((Width(w), _)) = self.term_size.get() {
             w as usize
         } else {
             80
@@ -470,7 +1209,8 @@ impl<T: Write> ProgressBar<T> {
     }
 }
 
-// Implement io::Writer
+// Implement io::Writer, the bytes are counted and discarded.
+// use `wrap_write` to forward them to another writer.
 impl<T: Write> Write for ProgressBar<T> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let n = buf.len();
@@ -484,7 +1224,13 @@ impl<T: Write> Write for ProgressBar<T> {
 
 #[cfg(test)]
 mod test {
-    use crate::{ProgressBar, Units};
+    use super::REFRESH_RATE;
+    use crate::config::Config;
+    use crate::fixture::{Broken, Shared};
+    use crate::style::visible_width;
+    use crate::title::Title;
+    use crate::tty::CursorGuard;
+    use crate::{EndState, ErrorPolicy, ProgressBar, Spinner, Style, Units};
     use std::time::Duration;
 
     #[test]
@@ -510,10 +1256,7 @@ mod test {
         let fmt = "[~> ]";
         let mut pb = ProgressBar::new(1);
         pb.format(fmt);
-        assert!(
-            pb.bar_start + &pb.bar_current + &pb.bar_current_n + &pb.bar_remain + &pb.bar_end
-                == fmt
-        );
+        assert!(pb.style().bar.concat() == fmt);
     }
 
     #[test]
@@ -539,9 +1282,9 @@ mod test {
     #[test]
     fn disable_speed_percent() {
         let mut out = Vec::new();
-        let mut pb = ProgressBar::on(&mut out, 10);
-        pb.show_speed = false;
-        pb.show_percent = false;
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_percent(false);
         pb.set_width(Some(80));
         pb.add(2);
         assert_eq!(
@@ -553,9 +1296,9 @@ mod test {
     #[test]
     fn disable_speed_time_left() {
         let mut out = Vec::new();
-        let mut pb = ProgressBar::on(&mut out, 10);
-        pb.show_speed = false;
-        pb.show_time_left = false;
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
         pb.set_width(Some(65));
         pb.add(1);
         assert_eq!(
@@ -567,12 +1310,12 @@ mod test {
     #[test]
     fn disable_percent_time_left() {
         let mut out = Vec::new();
-        let mut pb = ProgressBar::on(&mut out, 10);
-        pb.show_percent = false;
-        pb.show_time_left = false;
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_percent(false);
+        pb.set_show_time_left(false);
         pb.set_units(Units::Bytes);
         pb.set_width(Some(65));
-        pb.draw();
+        pb.draw().unwrap();
         assert_eq!(
             std::str::from_utf8(&out).unwrap(),
             "\r0 B / 10 B [---------------------------------------------] 0 B/s ",
@@ -582,13 +1325,13 @@ mod test {
     #[test]
     fn disable_suffix() {
         let mut out = Vec::new();
-        let mut pb = ProgressBar::on(&mut out, 10);
-        pb.show_speed = false;
-        pb.show_percent = false;
-        pb.show_time_left = false;
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_percent(false);
+        pb.set_show_time_left(false);
         pb.set_units(Units::Bytes);
         pb.set_width(Some(65));
-        pb.draw();
+        pb.draw().unwrap();
         assert_eq!(
             std::str::from_utf8(&out).unwrap(),
             "\r0 B / 10 B [--------------------------------------------------]  ",
@@ -599,18 +1342,19 @@ mod test {
     fn max_refresh_rate_finish() {
         let count = 500;
         let mut out = Vec::new();
-        let mut pb = ProgressBar::on(&mut out, count);
+        let mut pb = ProgressBar::with_config(&mut out, count, Config::default());
+        pb.tty = true;
         pb.format("╢▌▌░╟");
         pb.set_width(Some(80));
         pb.set_max_refresh_rate(Some(Duration::from_millis(100)));
-        pb.show_speed = false;
-        pb.show_time_left = false;
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
         pb.add(count / 2);
         pb.add(count / 2);
         let mut split = std::str::from_utf8(&out)
             .unwrap()
-            .trim_start_matches('\r')
-            .split('\r');
+            .split("\x1B[2K\r")
+            .skip(1);
         assert_eq!(
             split.next(),
             Some("250 / 500 ╢▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌░░░░░░░░░░░░░░░░░░░░░░░░░░░░░╟ 50.00 %")
@@ -620,4 +1364,406 @@ mod test {
             Some("500 / 500 ╢▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌▌╟ 100.00 %")
         );
     }
+
+    #[test]
+    fn refresh_rate() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 1000, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        for _ in 0..1000 {
+            pb.inc();
+        }
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.matches('\r').count() < 10, "should skip most updates");
+        assert!(out.ends_with("] 100.00 % "), "should draw the total");
+    }
+
+    #[test]
+    fn refresh_rate_after_burst() {
+        let out = Shared::default();
+        let mut pb = ProgressBar::with_config(out.clone(), 1_000_000, Config::default());
+        for _ in 0..100_000 {
+            pb.inc();
+        }
+        // each slow update is drawn, within the rate and a tick.
+        for _ in 0..5 {
+            std::thread::sleep(REFRESH_RATE + crate::clock::RESOLUTION * 2);
+            let drawn = out.text().len();
+            pb.inc();
+            assert!(out.text().len() > drawn, "should draw the slow update");
+        }
+    }
+
+    #[test]
+    fn wide_spinner() {
+        let out = Shared::default();
+        let mut pb = ProgressBar::with_config(out.clone(), 10, Config::default());
+        pb.set_style(&Style::new().spinner(&Spinner::moon()).show_tick(true))
+            .unwrap();
+        pb.set_width(Some(60));
+        pb.inc();
+        let line = out.text();
+        assert!(line.contains(&Spinner::moon().frames()[0]));
+        assert_eq!(visible_width(line.trim_start_matches('\r')), 60);
+    }
+
+    #[test]
+    fn spinner() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 0, Config::default());
+        pb.tty = true;
+        let spinner = Spinner::new(&["a", "b"], Duration::from_secs(3600));
+        pb.set_style(&Style::spinner_only().spinner(&spinner))
+            .unwrap();
+        pb.set_max_refresh_rate(None);
+        pb.tick();
+        pb.tick();
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "\x1B[2K\ra  \x1B[2K\ra  ",
+            "should turn with the time, not the ticks"
+        );
+    }
+
+    #[test]
+    fn broken_handle() {
+        let mut pb = ProgressBar::with_config(Broken, 10, Config::default());
+        assert!(pb.try_inc().is_err(), "try_ should return the error");
+        assert_eq!(pb.inc(), 2, "should not panic by default");
+        pb.finish_println("done");
+        assert!(pb.disabled, "should stop drawing");
+        assert!(pb.try_finish().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "write() fail")]
+    fn broken_handle_panic() {
+        let mut pb = ProgressBar::with_config(Broken, 10, Config::default());
+        pb.set_error_policy(ErrorPolicy::Panic);
+        pb.inc();
+    }
+
+    #[test]
+    fn abandon() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_width(Some(40));
+        pb.set_color(false);
+        pb.add(3);
+        pb.set_max_refresh_rate(Some(Duration::from_secs(60)));
+        pb.abandon_with_message("fail ");
+        pb.finish();
+        assert_eq!(pb.current, 3, "should stay at the current position");
+        assert!(pb.is_finish);
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap().rsplit('\r').next(),
+            Some("✖ fail 3 / 10 [====>----------] 30.00 % "),
+        );
+    }
+
+    #[test]
+    fn end_states() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_width(Some(40));
+        pb.add(6);
+        pb.finish_with_warning("slow ");
+        pb.finish();
+        assert_eq!(pb.current, 6, "should stay at the current position");
+        assert_eq!(pb.end_state(), Some(EndState::Warning));
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap().rsplit('\r').next(),
+            Some("⚠ slow 6 / 10 [========>------] 60.00 % "),
+            "should not color without a terminal"
+        );
+
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.tty = true;
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_width(Some(40));
+        pb.add(6);
+        pb.finish_with_warning("slow ");
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap().rsplit('\r').next(),
+            Some("\x1B[33m⚠\x1B[0m slow 6 / 10 [========>------] 60.00 %"),
+        );
+
+        let mut pb = ProgressBar::with_config(Vec::new(), 10, Config::default());
+        pb.finish();
+        assert_eq!(pb.end_state(), Some(EndState::Success));
+    }
+
+    #[test]
+    fn suspend() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb.set_width(Some(40));
+        pb.set_max_refresh_rate(Some(Duration::from_secs(60)));
+        pb.add(3);
+        assert_eq!(pb.suspend(|| 7), 7, "should return the result of `f`");
+        pb.finish();
+        let out = std::str::from_utf8(&out).unwrap();
+        let cleared = format!("\r{}\r\r3 / 10", " ".repeat(40));
+        assert!(out.contains(&cleared), "should clear and redraw: {:?}", out);
+
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.tty = true;
+        pb.add(3);
+        pb.suspend(|| ());
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(
+            out.contains("\x1B[2K\r\x1B[2K\r3 / 10"),
+            "should erase the line"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.set_json(true);
+        pb.message("copy ");
+        pb.add(4);
+        pb.finish_print("done");
+        pb.finish();
+        let out = std::str::from_utf8(&out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2, "should write a line per drawing: {:?}", out);
+        assert!(lines[0].starts_with(r#"{"pos":4,"total":10,"rate":"#));
+        assert!(lines[0].ends_with(r#","msg":"copy","state":"running"}"#));
+        assert!(lines[1].ends_with(r#""eta_secs":0,"msg":"done","state":"success"}"#));
+    }
+
+    #[test]
+    fn taskbar() {
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.tty = true;
+        pb.set_taskbar(true);
+        pb.set_max_refresh_rate(None);
+        pb.add(4);
+        pb.tick();
+        pb.finish();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.starts_with("\x1B]9;4;1;40\x1B\\\x1B[2K\r4 / 10"));
+        assert_eq!(
+            out.matches("9;4;1;40").count(),
+            1,
+            "should report changes only"
+        );
+        assert!(
+            out.ends_with("\x1B]9;4;0;0\x1B\\"),
+            "should clear the progress"
+        );
+
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.tty = true;
+        pb.set_taskbar(true);
+        pb.add(5);
+        pb.finish_with_error("");
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.contains("\x1B]9;4;2;50\x1B\\\x1B[2K\r"));
+        assert!(!out.contains("9;4;0;"), "should keep the error state");
+
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(&mut out, 10, Config::default());
+        pb.tty = true;
+        pb.set_taskbar(true);
+        pb.add(10);
+        pb.finish_println("done");
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(
+            out.ends_with("\x1B]9;4;0;0\x1B\\\ndone"),
+            "should clear the progress"
+        );
+    }
+
+    #[test]
+    fn title() {
+        let out = Shared::default();
+        let title = || out.text();
+        let mut pb = ProgressBar::with_config(Vec::new(), 10, Config::default());
+        pb.title = Some(Title::on(Box::new(out.clone()), "backup"));
+        pb.set_max_refresh_rate(None);
+        pb.add(4);
+        pb.tick();
+        pb.add(1);
+        assert_eq!(
+            title(),
+            "\x1B[22;0t\x1B]0;40% — backup\x07\x1B]0;50% — backup\x07",
+            "should save the previous title first, and set changes only"
+        );
+        drop(pb);
+        assert!(
+            title().ends_with("\x07\x1B[23;0t"),
+            "should restore on drop"
+        );
+
+        out.clear();
+        let mut pb = ProgressBar::with_config(Vec::new(), 10, Config::default());
+        pb.title = Some(Title::on(Box::new(out.clone()), ""));
+        pb.finish();
+        assert_eq!(title(), "\x1B[22;0t\x1B]0;100%\x07\x1B[23;0t");
+        drop(pb);
+        assert!(title().matches("23;0t").count() == 1, "should restore once");
+
+        let mut pb = ProgressBar::with_config(Vec::new(), 10, Config::default());
+        pb.set_title(Some("backup"));
+        assert!(pb.title.is_none(), "should need a terminal");
+    }
+
+    #[test]
+    fn hide_cursor() {
+        let out = Shared::default();
+        let mut pb = ProgressBar::with_config(out.clone(), 10, Config::default());
+        pb.set_hide_cursor(true);
+        pb.inc();
+        assert!(pb.cursor.is_none(), "should need a terminal");
+        assert!(!out.text().contains("\x1B[?25"));
+
+        let cursor = Shared::default();
+        pb.cursor = Some(CursorGuard::on(Box::new(cursor.clone())));
+        pb.finish_println("done");
+        assert!(pb.cursor.is_none(), "should show the cursor when done");
+        if cfg!(unix) {
+            assert_eq!(cursor.text(), "\x1B[?25l\x1B[?25h");
+        }
+        assert!(out.text().ends_with("\ndone"));
+    }
+
+    // a bar with `config`, that draws every update without the speed and
+    // the time left.
+    fn configured(out: &Shared, config: Config) -> ProgressBar<Shared> {
+        let mut pb = ProgressBar::with_config(out.clone(), 10, config);
+        pb.set_show_speed(false);
+        pb.set_show_time_left(false);
+        pb
+    }
+
+    #[test]
+    fn env_disable() {
+        let config = Config {
+            hidden: true,
+            ..Config::default()
+        };
+        let out = Shared::default();
+        let mut pb = configured(&out, config.clone());
+        pb.inc();
+        pb.finish_print("done");
+        assert_eq!(out.text(), "done\n", "should only write the text");
+
+        let out = Shared::default();
+        let mut pb = configured(&out, config);
+        pb.inc();
+        pb.finish_println("done");
+        assert_eq!(out.text(), "done\n");
+    }
+
+    #[test]
+    fn env_disable_json() {
+        let out = Shared::default();
+        let config = Config {
+            hidden: true,
+            ..Config::default()
+        };
+        let mut pb = configured(&out, config);
+        pb.set_json(true);
+        pb.add(4);
+        pb.finish_print("done");
+        let out = out.text();
+        assert_eq!(out.lines().count(), 2, "should write the JSON lines");
+        assert!(out.lines().all(|l| l.starts_with('{') && l.ends_with('}')));
+        assert!(out.ends_with("\"msg\":\"done\",\"state\":\"success\"}\n"));
+    }
+
+    #[test]
+    fn env_force_tty() {
+        let out = Shared::default();
+        let config = Config {
+            force_tty: true,
+            ..Config::default()
+        };
+        let mut pb = configured(&out, config);
+        pb.set_width(Some(40));
+        pb.inc();
+        assert_eq!(
+            out.text(),
+            "\x1B[2K\r1 / 10 [==>-------------------] 10.00 %"
+        );
+    }
+
+    #[test]
+    fn env_width() {
+        let out = Shared::default();
+        let config = Config {
+            width: Some(30),
+            ..Config::default()
+        };
+        let mut pb = configured(&out, config);
+        pb.inc();
+        assert_eq!(out.text(), "\r1 / 10 [=>----------] 10.00 % ");
+        pb.set_width(Some(40));
+        assert_eq!(pb.width(), 40, "should be overridden by the setter");
+    }
+
+    #[test]
+    fn env_refresh_rate() {
+        let out = Shared::default();
+        let config = Config {
+            refresh_rate: Some(Duration::from_secs(60)),
+            ..Config::default()
+        };
+        let mut pb = configured(&out, config);
+        pb.inc();
+        pb.inc();
+        assert_eq!(
+            out.text().matches('\r').count(),
+            1,
+            "should skip the second"
+        );
+    }
+
+    #[test]
+    fn env_no_color() {
+        let out = Shared::default();
+        let config = Config {
+            force_tty: true,
+            color: false,
+            ..Config::default()
+        };
+        let mut pb = configured(&out, config);
+        pb.set_width(Some(40));
+        pb.add(6);
+        pb.finish_with_warning("slow ");
+        assert_eq!(
+            out.text().rsplit('\r').next(),
+            Some("⚠ slow 6 / 10 [========>------] 60.00 %"),
+        );
+    }
+
+    #[test]
+    fn finish_print_after_pause() {
+        let out = Shared::default();
+        let mut pb = ProgressBar::with_config(out.clone(), 10, Config::default());
+        pb.add(3);
+        // past the default max refresh rate.
+        std::thread::sleep(Duration::from_millis(60));
+        pb.finish_print("done");
+        assert_eq!(
+            out.text().rsplit('\r').next().map(str::trim_end),
+            Some("done"),
+            "should keep the text"
+        );
+    }
 }
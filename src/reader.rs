@@ -0,0 +1,113 @@
+use crate::ProgressBar;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+/// ProgressReader wraps a reader and adds the bytes read from it to a
+/// `ProgressBar`, see `ProgressBar::wrap_read`.
+///
+/// Seeking the reader sets the bar to the new position.
+pub struct ProgressReader<'a, R, T: Write> {
+    inner: R,
+    pb: &'a mut ProgressBar<T>,
+}
+
+impl<T: Write> ProgressBar<T> {
+    /// Wrap `reader` so reading from it updates the bar.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io;
+    /// use pbr::{ProgressBar, Units};
+    ///
+    /// let file = File::open("/usr/share/dict/words").unwrap();
+    /// let n_bytes = file.metadata().unwrap().len();
+    /// let mut out = File::create("copy-words").unwrap();
+    ///
+    /// let mut pb = ProgressBar::new(n_bytes);
+    /// pb.set_units(Units::Bytes);
+    /// io::copy(&mut pb.wrap_read(file), &mut out).unwrap();
+    /// pb.finish_print("done");
+    /// ```
+    pub fn wrap_read<R>(&mut self, reader: R) -> ProgressReader<'_, R, T> {
+        ProgressReader {
+            inner: reader,
+            pb: self,
+        }
+    }
+}
+
+impl<'a, R, T: Write> ProgressReader<'a, R, T> {
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader does not update the bar.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<'a, R: Read, T: Write> Read for ProgressReader<'a, R, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pb.add(n as u64);
+        Ok(n)
+    }
+}
+
+impl<'a, R: BufRead, T: Write> BufRead for ProgressReader<'a, R, T> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.pb.add(amt as u64);
+    }
+}
+
+impl<'a, R: Seek, T: Write> Seek for ProgressReader<'a, R, T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = self.inner.seek(pos)?;
+        self.pb.set(pos);
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::ProgressBar;
+    use std::io::{self, BufRead, Cursor, Seek, SeekFrom};
+
+    #[test]
+    fn copy() {
+        let data = vec![7u8; 1000];
+        let mut out = Vec::new();
+        let mut pb = ProgressBar::with_config(Vec::new(), 1000, Config::default());
+        let n = io::copy(&mut pb.wrap_read(Cursor::new(&data)), &mut out).unwrap();
+        assert_eq!(n, 1000);
+        assert_eq!(out, data);
+        assert_eq!(pb.current, 1000, "should count the bytes read");
+    }
+
+    #[test]
+    fn buf_read_and_seek() {
+        let mut pb = ProgressBar::with_config(Vec::new(), 12, Config::default());
+        let mut r = pb.wrap_read(Cursor::new("one\ntwo\nthree"));
+        let mut line = String::new();
+        r.read_line(&mut line).unwrap();
+        assert_eq!(line, "one\n");
+        r.seek(SeekFrom::End(-5)).unwrap();
+        assert_eq!(pb.current, 8, "should set the position on seek");
+    }
+}
@@ -0,0 +1,284 @@
+use crate::config::Config;
+use crate::tty::{clear_line, is_terminal, Stream};
+use crossbeam_channel::{unbounded, RecvTimeoutError, Sender};
+use std::io::{self, Write};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Spinner is a set of frames that are shown one after the other, each
+/// for the same interval.
+///
+/// The frame is picked by the time that passed, so a spinner turns at its
+/// intended speed however often it is drawn. Use it standalone with
+/// `frame`, let it draw itself on a terminal with `spin`, or as the tick
+/// box of a bar with `Style::spinner`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::{ProgressBar, Spinner, Stream, Style};
+/// use std::time::Instant;
+///
+/// // standalone
+/// let spinner = Spinner::dots();
+/// let start = Instant::now();
+/// print!("\r{} waiting", spinner.frame(start.elapsed()));
+///
+/// // drawn by a background thread
+/// let spinning = Spinner::dots().spin(Stream::Stderr, "waiting");
+/// spinning.finish_print("done");
+///
+/// // as the tick box of a bar
+/// let mut pb = ProgressBar::new(0);
+/// pb.set_style(&Style::spinner_only().spinner(&Spinner::moon()))
+///     .unwrap();
+/// pb.message("waiting for the lock");
+/// pb.tick();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spinner {
+    frames: Vec<String>,
+    interval: Duration,
+}
+
+impl Spinner {
+    /// Create a spinner that shows each of `frames` for `interval`.
+    pub fn new(frames: &[&str], interval: Duration) -> Spinner {
+        Spinner {
+            frames: frames.iter().map(|f| f.to_string()).collect(),
+            interval,
+        }
+    }
+
+    /// `⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏`, every 80ms.
+    pub fn dots() -> Spinner {
+        Spinner::new(
+            &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            Duration::from_millis(80),
+        )
+    }
+
+    /// `-\|/`, every 130ms.
+    pub fn line() -> Spinner {
+        Spinner::new(&["-", "\\", "|", "/"], Duration::from_millis(130))
+    }
+
+    /// `◜◠◝◞◡◟`, every 100ms.
+    pub fn arc() -> Spinner {
+        Spinner::new(&["◜", "◠", "◝", "◞", "◡", "◟"], Duration::from_millis(100))
+    }
+
+    /// A `[=== ]` bar that bounces between its ends, every 80ms.
+    pub fn bouncing_bar() -> Spinner {
+        Spinner::new(
+            &[
+                "[    ]", "[=   ]", "[==  ]", "[=== ]", "[ ===]", "[  ==]", "[   =]", "[    ]",
+                "[   =]", "[  ==]", "[ ===]", "[====]", "[=== ]", "[==  ]", "[=   ]",
+            ],
+            Duration::from_millis(80),
+        )
+    }
+
+    /// `🕛🕐🕑…`, every 100ms.
+    pub fn clock() -> Spinner {
+        Spinner::new(
+            &[
+                "🕛", "🕐", "🕑", "🕒", "🕓", "🕔", "🕕", "🕖", "🕗", "🕘", "🕙", "🕚",
+            ],
+            Duration::from_millis(100),
+        )
+    }
+
+    /// `🌑🌒🌓🌔🌕🌖🌗🌘`, every 80ms.
+    pub fn moon() -> Spinner {
+        Spinner::new(
+            &["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"],
+            Duration::from_millis(80),
+        )
+    }
+
+    /// The frames of the spinner.
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    /// How long each frame is shown.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The frame shown `elapsed` after the spinner started, or `""` if it
+    /// has no frames.
+    pub fn frame(&self, elapsed: Duration) -> &str {
+        match self.frames.len() {
+            0 => "",
+            n => &self.frames[frame_index(elapsed, self.interval, n)],
+        }
+    }
+
+    /// Draw the spinner and `message` on `stream` from a background thread,
+    /// each frame at its interval, until the returned `Spinning` is
+    /// finished or dropped.
+    ///
+    /// Nothing is drawn if `stream` is not a terminal, or if the bars are
+    /// hidden by the environment, see the crate docs. The text of
+    /// `Spinning::finish_print` is still written on a non-terminal.
+    pub fn spin(&self, stream: Stream, message: &str) -> Spinning {
+        let config = Config::from_env();
+        let out: Box<dyn Write + Send> = match stream {
+            Stream::Stdout => Box::new(io::stdout()),
+            Stream::Stderr => Box::new(io::stderr()),
+        };
+        let draw = !config.hidden && (config.force_tty || is_terminal(stream));
+        self.spin_on(out, stream, draw, !config.hidden, message)
+    }
+
+    // spin on `out`, drawing the frames if `draw` and writing the text of
+    // `finish_print` if `print`.
+    fn spin_on(
+        &self,
+        mut out: Box<dyn Write + Send>,
+        stream: Stream,
+        draw: bool,
+        print: bool,
+        message: &str,
+    ) -> Spinning {
+        let (tx, rx) = unbounded();
+        let spinner = self.clone();
+        let mut message = message.to_owned();
+        let thread = thread::spawn(move || {
+            let start = Instant::now();
+            let mut drawn = false;
+            let end = loop {
+                if draw {
+                    let frame = spinner.frame(start.elapsed());
+                    let line = format!("\r{}{} {}", clear_line(stream), frame, message);
+                    let _ = out.write_all(line.as_bytes()).and_then(|_| out.flush());
+                    drawn = true;
+                }
+                let wait = spinner.interval.max(Duration::from_millis(1));
+                match rx.recv_timeout(wait) {
+                    Ok(Spin::Message(m)) => message = m,
+                    Ok(Spin::Finish(text)) => break text,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break None,
+                }
+            };
+            let mut last = String::new();
+            if drawn {
+                last = format!("\r{}", clear_line(stream));
+            }
+            if let Some(text) = end.filter(|_| print) {
+                last = last + &text + "\n";
+            }
+            let _ = out.write_all(last.as_bytes()).and_then(|_| out.flush());
+        });
+        Spinning {
+            tx,
+            thread: Some(thread),
+        }
+    }
+}
+
+// the messages to the thread of a `Spinning`.
+enum Spin {
+    Message(String),
+    Finish(Option<String>),
+}
+
+/// Spinning is a spinner drawn by a background thread, see `Spinner::spin`.
+///
+/// The line of the spinner is cleared when it is finished or dropped.
+pub struct Spinning {
+    tx: Sender<Spin>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Spinning {
+    /// Set the message shown after the spinner.
+    pub fn set_message(&self, message: &str) {
+        let _ = self.tx.send(Spin::Message(message.to_owned()));
+    }
+
+    /// Stop the spinner and clear its line.
+    pub fn finish(mut self) {
+        self.stop(None);
+    }
+
+    /// Stop the spinner and write `s` in place of its line.
+    pub fn finish_print(mut self, s: &str) {
+        self.stop(Some(s.to_owned()));
+    }
+
+    fn stop(&mut self, text: Option<String>) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.tx.send(Spin::Finish(text));
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Spinning {
+    fn drop(&mut self) {
+        self.stop(None);
+    }
+}
+
+// the index of the frame shown `elapsed` after the start, out of `n`
+// frames shown for `interval` each.
+pub(crate) fn frame_index(elapsed: Duration, interval: Duration, n: usize) -> usize {
+    if interval.is_zero() {
+        return 0;
+    }
+    (elapsed.as_nanos() / interval.as_nanos() % n as u128) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::Spinner;
+    use crate::fixture::Shared;
+    use crate::tty::{clear_line, Stream};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn frame() {
+        let sp = Spinner::new(&["a", "b", "c"], Duration::from_millis(100));
+        let at = |ms| sp.frame(Duration::from_millis(ms));
+        assert_eq!(
+            (at(0), at(99), at(100), at(250), at(300)),
+            ("a", "a", "b", "c", "a")
+        );
+        assert_eq!(
+            Spinner::new(&[], Duration::from_millis(100)).frame(Duration::ZERO),
+            ""
+        );
+        assert_eq!(
+            Spinner::new(&["a", "b"], Duration::ZERO).frame(Duration::from_secs(1)),
+            "a"
+        );
+    }
+
+    #[test]
+    fn spin() {
+        let out = Shared::default();
+        let sp = Spinner::new(&["a", "b"], Duration::from_millis(5));
+        let spinning = sp.spin_on(Box::new(out.clone()), Stream::Stdout, true, true, "wait");
+        thread::sleep(Duration::from_millis(30));
+        spinning.set_message("more");
+        thread::sleep(Duration::from_millis(30));
+        spinning.finish_print("done");
+        let text = out.text();
+        assert!(text.contains("a wait"), "should draw the frames");
+        assert!(text.contains("b wait"), "should turn by itself");
+        assert!(text.contains("more"), "should show the new message");
+        let last = format!("\r{}done\n", clear_line(Stream::Stdout));
+        assert!(text.ends_with(&last), "should print in place of the line");
+
+        let out = Shared::default();
+        let spinning = sp.spin_on(Box::new(out.clone()), Stream::Stdout, false, true, "wait");
+        thread::sleep(Duration::from_millis(20));
+        drop(spinning);
+        assert_eq!(out.text(), "", "should not draw on a non-terminal");
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{Spinner, Units};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+pub(crate) const FORMAT: &str = "[=>-]";
+pub(crate) const TICK_FORMAT: &str = "\\|/-";
+
+/// Style is the look of a bar: the boxes it shows, the format of the bar
+/// and of the tick, and the units.
+///
+/// A style is a plain value, so it can be cloned and shared between bars,
+/// see `ProgressBar::set_style`, `ProgressBarBuilder::style` and
+/// `MultiBar::set_style`. It is checked when it is applied to a bar.
+///
+/// Presets for common looks are `npm`, `pip`, `cargo`, `download`,
+/// `spinner_only` and `minimal`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::{ProgressBar, Style, Units};
+///
+/// let style = Style::new()
+///     .format("╢▌▌░╟")
+///     .units(Units::Bytes)
+///     .show_time_left(false);
+///
+/// let mut a = ProgressBar::new(100);
+/// a.set_style(&style).unwrap();
+/// let mut b = ProgressBar::new(200);
+/// b.set_style(&style).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    // the characters of the bar format: start, current, current_n,
+    // remain and end.
+    pub(crate) bar: Vec<String>,
+    pub(crate) tick: Vec<String>,
+    // how long each tick frame is shown, or `None` to show the next one
+    // on every tick.
+    pub(crate) tick_interval: Option<Duration>,
+    pub(crate) units: Units,
+    pub(crate) show_bar: bool,
+    pub(crate) show_speed: bool,
+    pub(crate) show_percent: bool,
+    pub(crate) show_counter: bool,
+    pub(crate) show_time_left: bool,
+    pub(crate) show_tick: bool,
+    pub(crate) show_message: bool,
+}
+
+// chainable setters of the `show_*` flags of a style.
+macro_rules! show_flags {
+    ($($(#[$doc:meta])* $name:ident;)*) => {$(
+        $(#[$doc])*
+        pub fn $name(mut self, show: bool) -> Style {
+            self.$name = show;
+            self
+        }
+    )*};
+}
+
+impl Style {
+    /// Create the default style, `[=>-]` with all boxes but the tick.
+    pub fn new() -> Style {
+        Style {
+            bar: chars(FORMAT),
+            tick: chars(TICK_FORMAT),
+            tick_interval: None,
+            units: Units::Default,
+            show_bar: true,
+            show_speed: true,
+            show_percent: true,
+            show_counter: true,
+            show_time_left: true,
+            show_tick: false,
+            show_message: true,
+        }
+    }
+
+    /// Set the format of the bar, 5 characters for its start, the done
+    /// part, the head of the done part, the remaining part and its end.
+    /// Default is `[=>-]`.
+    pub fn format(mut self, fmt: &str) -> Style {
+        self.bar = chars(fmt);
+        self
+    }
+
+    /// Set the frames of the tick box, one for each character. Default is
+    /// `\|/-`. It does not show the tick box, see `show_tick`.
+    pub fn tick_format(mut self, tick_fmt: &str) -> Style {
+        self.tick = chars(tick_fmt);
+        self.tick_interval = None;
+        self
+    }
+
+    /// Use the frames of `spinner` in the tick box, each for the interval
+    /// of the spinner instead of until the next tick. It does not show
+    /// the tick box, see `show_tick`.
+    pub fn spinner(mut self, spinner: &Spinner) -> Style {
+        self.tick = spinner.frames().to_vec();
+        self.tick_interval = Some(spinner.interval());
+        self
+    }
+
+    /// Set the units of the counter and speed boxes, default is simple
+    /// numbers.
+    pub fn units(mut self, units: Units) -> Style {
+        self.units = units;
+        self
+    }
+
+    show_flags! {
+        /// Set whether to show the bar, default is `true`.
+        show_bar;
+        /// Set whether to show the speed box, default is `true`.
+        show_speed;
+        /// Set whether to show the percent box, default is `true`.
+        show_percent;
+        /// Set whether to show the counter box, default is `true`.
+        show_counter;
+        /// Set whether to show the time left box, default is `true`.
+        show_time_left;
+        /// Set whether to show the tick box, default is `false`.
+        show_tick;
+        /// Set whether to show the message box, default is `true`.
+        show_message;
+    }
+
+    /// npm style, a spinner and a `|###---|` bar, e.g.
+    /// `\ fetch |######-------|`.
+    pub fn npm() -> Style {
+        Style::new()
+            .format("|#--|")
+            .show_tick(true)
+            .show_speed(false)
+            .show_percent(false)
+            .show_counter(false)
+            .show_time_left(false)
+    }
+
+    /// pip style, a thin bar with the size, speed and time left of a
+    /// download, e.g. `1.00 MB / 2.00 MB [━━━━━━╸      ] 512.00 KB/s 2s`.
+    pub fn pip() -> Style {
+        Style::new()
+            .format("[━╸ ]")
+            .units(Units::Bytes)
+            .show_percent(false)
+    }
+
+    /// cargo style, a bar with the count of done items, e.g.
+    /// `Building 120 / 180 [=======>     ]`.
+    pub fn cargo() -> Style {
+        Style::new()
+            .format("[=> ]")
+            .show_speed(false)
+            .show_percent(false)
+            .show_time_left(false)
+    }
+
+    /// Download style, all boxes in bytes, e.g.
+    /// `1.00 MB / 2.00 MB [█████▌░░░░░] 50.00 % 512.00 KB/s 2s`.
+    pub fn download() -> Style {
+        Style::new().format("[█▌░]").units(Units::Bytes)
+    }
+
+    /// Only a spinner and the message, for work of unknown size, e.g.
+    /// `| waiting for the lock`. Move the spinner with `ProgressBar::tick`,
+    /// or use a timed one with `spinner`. For a spinner that turns without
+    /// ticks, see `Spinner::spin`.
+    pub fn spinner_only() -> Style {
+        Style::new()
+            .show_tick(true)
+            .show_bar(false)
+            .show_speed(false)
+            .show_percent(false)
+            .show_counter(false)
+            .show_time_left(false)
+    }
+
+    /// Only the bar and the percent, e.g. `[=====>-----] 50.00 %`.
+    pub fn minimal() -> Style {
+        Style::new()
+            .show_speed(false)
+            .show_counter(false)
+            .show_time_left(false)
+            .show_message(false)
+    }
+
+    // check that the style can be drawn.
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if self.bar.len() != 5 {
+            return Err(ConfigError::Format(self.bar.concat()));
+        }
+        if self.tick.is_empty() {
+            return Err(ConfigError::TickFormat);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style::new()
+    }
+}
+
+pub(crate) fn chars(s: &str) -> Vec<String> {
+    s.chars().map(String::from).collect()
+}
+
+// the number of columns `s` takes on a terminal, without its control
+// characters and CSI sequences (e.g. colors). Wide characters, like the
+// emojis of `Spinner::clock` and `Spinner::moon`, take 2.
+pub(crate) fn visible_width(s: &str) -> usize {
+    let mut n = 0;
+    let mut it = s.chars();
+    while let Some(c) = it.next() {
+        if c == '\x1B' && it.clone().next() == Some('[') {
+            // skip the `[`, and up to the final byte of the sequence.
+            it.next();
+            it.by_ref().find(|c| ('@'..='~').contains(c));
+        } else if is_wide(c) {
+            n += 2;
+        } else if !c.is_control() {
+            n += 1;
+        }
+    }
+    n
+}
+
+// whether `c` takes 2 columns: East Asian wide and fullwidth characters,
+// and the emojis, with the common ranges of Unicode.
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD)
+}
+
+/// ConfigError is returned for a configuration that a bar can not be
+/// drawn with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The format of the bar is not 5 characters long.
+    Format(String),
+    /// The tick format has no frames.
+    TickFormat,
+    /// The width is 0.
+    Width,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Format(fmt) => {
+                write!(f, "bar format {:?} is not 5 characters long", fmt)
+            }
+            ConfigError::TickFormat => write!(f, "tick format is empty"),
+            ConfigError::Width => write!(f, "width is 0"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod test {
+    use super::{visible_width, ConfigError, Style};
+
+    #[test]
+    fn presets() {
+        for style in [
+            Style::npm(),
+            Style::pip(),
+            Style::cargo(),
+            Style::download(),
+            Style::spinner_only(),
+            Style::minimal(),
+        ] {
+            assert_eq!(style.validate(), Ok(()), "{:?}", style);
+        }
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(Style::new().validate(), Ok(()));
+        assert_eq!(Style::new().format("╢▌▌░╟").validate(), Ok(()));
+        assert_eq!(
+            Style::new().format("[=>]").validate(),
+            Err(ConfigError::Format("[=>]".to_owned()))
+        );
+        assert_eq!(
+            Style::new().tick_format("").validate(),
+            Err(ConfigError::TickFormat)
+        );
+    }
+
+    #[test]
+    fn width() {
+        assert_eq!(visible_width("✔ done"), 6);
+        assert_eq!(visible_width("\x1B[32m✔\x1B[0m done"), 6);
+        assert_eq!(visible_width("\r10 / 10"), 7);
+        assert_eq!(
+            visible_width("🌑 wait"),
+            7,
+            "should count wide glyphs twice"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::tty::Stream;
+use std::io::{self, Write};
+
+// Title sets the title of the terminal window to the progress of a bar,
+// see `ProgressBar::set_title`.
+//
+// The title is written to the stream of the bar with a writer of its own,
+// so it is restored even when the bar is dropped, and only when it
+// changed. Terminals can not be asked for their title, so the previous
+// one is saved on the title stack of the terminal (xterm `CSI 22 t`), and
+// restored from it.
+pub(crate) struct Title {
+    label: String,
+    out: Box<dyn Write + Send + Sync>,
+    // the title that was set last, while the previous one is saved and
+    // still to be restored.
+    shown: Option<String>,
+}
+
+impl Title {
+    // a title written to `stream`.
+    pub(crate) fn open(stream: Stream, label: &str) -> Title {
+        let out: Box<dyn Write + Send + Sync> = match stream {
+            Stream::Stdout => Box::new(io::stdout()),
+            Stream::Stderr => Box::new(io::stderr()),
+        };
+        Title::on(out, label)
+    }
+
+    pub(crate) fn on(out: Box<dyn Write + Send + Sync>, label: &str) -> Title {
+        Title {
+            label: label.to_owned(),
+            out,
+            shown: None,
+        }
+    }
+
+    pub(crate) fn label(&self) -> &str {
+        &self.label
+    }
+
+    // set the title to `percent` (if known) and the label, unless it is
+    // already. Errors are ignored, the title is not worth stopping the
+    // bar for.
+    pub(crate) fn set(&mut self, percent: Option<u64>) {
+        let title = match (percent, self.label.is_empty()) {
+            (Some(p), true) => format!("{}%", p),
+            (Some(p), false) => format!("{}% — {}", p, self.label),
+            (None, _) => self.label.clone(),
+        };
+        let mut out = String::new();
+        match self.shown.as_deref() {
+            Some(shown) if shown == title => return,
+            Some(_) => {}
+            None => out += "\x1B[22;0t",
+        }
+        out += &format!("\x1B]0;{}\x07", title);
+        let _ = printfl!(self.out, "{}", out);
+        self.shown = Some(title);
+    }
+
+    // restore the title from before the first `set`.
+    pub(crate) fn restore(&mut self) {
+        if self.shown.take().is_some() {
+            let _ = printfl!(self.out, "\x1B[23;0t");
+        }
+    }
+}
+
+impl Drop for Title {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
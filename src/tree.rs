@@ -0,0 +1,347 @@
+use crate::multi::BarFactory;
+use crate::{Pipe, ProgressBar};
+use std::sync::{Arc, Mutex};
+
+// indentation added to the message of a node for every level of depth.
+const INDENT: &str = "  ";
+
+/// ProgressNode is a node in a tree of bars drawn by a `MultiBar`.
+///
+/// The progress of a node without children is its own `current / total`.
+/// The progress of a node with children is the weighted average of the
+/// progress of its children, and its bar is updated whenever one of them
+/// changes.
+///
+/// `ProgressNode` is cheap to clone, and all clones refer to the same node,
+/// so it can be moved to the thread doing the work.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::thread;
+/// use pbr::MultiBar;
+///
+/// let mb = MultiBar::new();
+/// let root = mb.create_tree(100);
+/// root.message("build ");
+/// root.collapse_finished(true);
+///
+/// let fetch = root.create_child(0.3, 20);
+/// fetch.message("fetch ");
+/// let compile = root.create_child(0.7, 100);
+/// compile.message("compile ");
+///
+/// thread::spawn(move || {
+///     for _ in 0..20 {
+///         fetch.inc();
+///     }
+///     fetch.finish();
+///     for _ in 0..100 {
+///         compile.inc();
+///     }
+///     compile.finish();
+///     root.finish();
+/// });
+///
+/// mb.listen();
+/// ```
+#[derive(Clone)]
+pub struct ProgressNode {
+    tree: Arc<Mutex<Tree>>,
+    id: usize,
+}
+
+struct Tree {
+    factory: BarFactory,
+    nodes: Vec<Node>,
+    collapse: bool,
+}
+
+struct Node {
+    bar: ProgressBar<Pipe>,
+    // the line of the bar in the MultiBar.
+    level: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    weight: f64,
+    depth: usize,
+}
+
+impl ProgressNode {
+    pub(crate) fn root(factory: BarFactory, total: u64) -> ProgressNode {
+        let (level, bar) = factory.create_bar(None, total);
+        let tree = Tree {
+            factory,
+            nodes: vec![Node {
+                bar,
+                level,
+                parent: None,
+                children: Vec::new(),
+                weight: 1.0,
+                depth: 0,
+            }],
+            collapse: false,
+        };
+        ProgressNode {
+            tree: Arc::new(Mutex::new(tree)),
+            id: 0,
+        }
+    }
+
+    /// Create a child node with the given weight and total.
+    ///
+    /// The child is drawn below the last line of this node subtree, and
+    /// `weight` is its share in the progress of this node, relative to
+    /// the weights of its siblings. e.g. children with weights `0.3` and
+    /// `0.7` (or `3` and `7`) are 30% and 70% of the work.
+    ///
+    /// Once a node has children, its own value is driven by them, and
+    /// calling `inc`, `add` or `set` on it has no lasting effect.
+    pub fn create_child(&self, weight: f64, total: u64) -> ProgressNode {
+        let mut tree = self.tree.lock().unwrap();
+        let after = tree.last_level(self.id);
+        let (level, mut bar) = tree.factory.create_bar(Some(after), total);
+        let depth = tree.nodes[self.id].depth + 1;
+        bar.message(&INDENT.repeat(depth));
+
+        let id = tree.nodes.len();
+        tree.nodes.push(Node {
+            bar,
+            level,
+            parent: Some(self.id),
+            children: Vec::new(),
+            weight: weight.max(0.0),
+            depth,
+        });
+        tree.nodes[self.id].children.push(id);
+        tree.propagate(id);
+
+        ProgressNode {
+            tree: self.tree.clone(),
+            id,
+        }
+    }
+
+    /// Hide the lines of the descendants of a node when it finishes,
+    /// default is `false`. The setting is shared by the whole tree.
+    pub fn collapse_finished(&self, collapse: bool) {
+        self.tree.lock().unwrap().collapse = collapse;
+    }
+
+    /// Set the message of the node, see `ProgressBar::message`.
+    ///
+    /// The message is indented according to the depth of the node.
+    pub fn message(&self, message: &str) {
+        let mut tree = self.tree.lock().unwrap();
+        let node = &mut tree.nodes[self.id];
+        let indent = INDENT.repeat(node.depth);
+        node.bar.message(&(indent + message));
+    }
+
+    /// Add to current value, and update the ancestors of the node.
+    pub fn add(&self, i: u64) -> u64 {
+        self.update(|bar| bar.add(i))
+    }
+
+    /// Manually set the current value, and update the ancestors of the node.
+    pub fn set(&self, i: u64) -> u64 {
+        self.update(|bar| bar.set(i))
+    }
+
+    /// Increment current value, and update the ancestors of the node.
+    pub fn inc(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Update the node bar even though no progress are made,
+    /// see `ProgressBar::tick`.
+    pub fn tick(&self) {
+        self.tree.lock().unwrap().nodes[self.id].bar.tick();
+    }
+
+    /// Progress of the node, between `0.0` and `1.0`.
+    pub fn fraction(&self) -> f64 {
+        self.tree.lock().unwrap().fraction(self.id)
+    }
+
+    /// Finish the node and all its unfinished descendants.
+    ///
+    /// Nodes are not finished when their children finish, every
+    /// node in the tree (or one of its ancestors) must be finished
+    /// to notify the `MultiBar` about it.
+    pub fn finish(&self) {
+        let mut tree = self.tree.lock().unwrap();
+        tree.finish(self.id);
+        tree.propagate(self.id);
+    }
+
+    /// Abandon the node and all its unfinished descendants, see
+    /// `ProgressBar::abandon`.
+    pub fn abandon(&self) {
+        self.tree.lock().unwrap().abandon(self.id);
+    }
+
+    /// Call `f` with the underlying `ProgressBar` of the node. Useful for
+    /// configuring its format and boxes.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pbr::MultiBar;
+    ///
+    /// let mb = MultiBar::new();
+    /// let root = mb.create_tree(100);
+    /// root.with_bar(|pb| {
+    ///     pb.format("╢▌▌░╟");
+    ///     pb.set_show_speed(false);
+    /// });
+    /// ```
+    pub fn with_bar<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut ProgressBar<Pipe>) -> R,
+    {
+        f(&mut self.tree.lock().unwrap().nodes[self.id].bar)
+    }
+
+    fn update<F>(&self, f: F) -> u64
+    where
+        F: FnOnce(&mut ProgressBar<Pipe>) -> u64,
+    {
+        let mut tree = self.tree.lock().unwrap();
+        let current = f(&mut tree.nodes[self.id].bar);
+        tree.propagate(self.id);
+        current
+    }
+}
+
+impl Tree {
+    fn fraction(&self, id: usize) -> f64 {
+        let node = &self.nodes[id];
+        if node.children.is_empty() {
+            if node.bar.total == 0 {
+                return if node.bar.is_finish { 1.0 } else { 0.0 };
+            }
+            return (node.bar.current as f64 / node.bar.total as f64).min(1.0);
+        }
+        let weights: f64 = node.children.iter().map(|&c| self.nodes[c].weight).sum();
+        if weights <= 0.0 {
+            return 0.0;
+        }
+        node.children
+            .iter()
+            .map(|&c| self.nodes[c].weight * self.fraction(c))
+            .sum::<f64>()
+            / weights
+    }
+
+    // propagate updates the bars of the ancestors of `id`.
+    fn propagate(&mut self, mut id: usize) {
+        while let Some(parent) = self.nodes[id].parent {
+            if !self.nodes[parent].bar.is_finish {
+                let fraction = self.fraction(parent);
+                let bar = &mut self.nodes[parent].bar;
+                let current = (fraction * bar.total as f64).round() as u64;
+                bar.set(current);
+            }
+            id = parent;
+        }
+    }
+
+    fn finish(&mut self, id: usize) {
+        for child in self.nodes[id].children.clone() {
+            self.finish(child);
+        }
+        // hide the subtree before the bar notify the MultiBar,
+        // so the last drawing is already collapsed.
+        if self.collapse {
+            self.hide_descendants(id);
+        }
+        let bar = &mut self.nodes[id].bar;
+        if !bar.is_finish {
+            bar.finish();
+        }
+    }
+
+    fn abandon(&mut self, id: usize) {
+        for child in self.nodes[id].children.clone() {
+            self.abandon(child);
+        }
+        self.nodes[id].bar.abandon();
+    }
+
+    fn hide_descendants(&self, id: usize) {
+        let mut layout = self.factory.layout().lock().unwrap();
+        let mut stack = self.nodes[id].children.clone();
+        while let Some(c) = stack.pop() {
+            layout.set_hidden(self.nodes[c].level, true);
+            stack.extend_from_slice(&self.nodes[c].children);
+        }
+    }
+
+    // the level of the last line in the subtree of `id`.
+    fn last_level(&self, id: usize) -> usize {
+        match self.nodes[id].children.last() {
+            Some(&c) => self.last_level(c),
+            None => self.nodes[id].level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::MultiBar;
+
+    #[test]
+    fn weighted_fraction() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let root = mb.create_tree(100);
+        let a = root.create_child(0.3, 10);
+        let b = root.create_child(0.7, 100);
+
+        a.add(5);
+        assert!((root.fraction() - 0.15).abs() < 1e-9);
+        b.add(50);
+        assert!((root.fraction() - 0.5).abs() < 1e-9);
+        a.finish();
+        assert!((root.fraction() - 0.65).abs() < 1e-9);
+        assert_eq!(root.with_bar(|pb| pb.current), 65);
+
+        root.finish();
+        assert_eq!(b.fraction(), 1.0);
+        mb.listen();
+    }
+
+    #[test]
+    fn nested_children() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let root = mb.create_tree(10);
+        let a = root.create_child(1.0, 1);
+        let a1 = a.create_child(1.0, 4);
+        let a2 = a.create_child(3.0, 4);
+        root.create_child(1.0, 1).finish();
+
+        a2.add(2);
+        assert!((a.fraction() - 0.375).abs() < 1e-9);
+        assert!((root.fraction() - 0.6875).abs() < 1e-9);
+        a1.finish();
+        a2.finish();
+        assert_eq!(a.fraction(), 1.0);
+
+        root.collapse_finished(true);
+        root.finish();
+        mb.listen();
+    }
+
+    #[test]
+    fn abandon() {
+        let mb = MultiBar::with_config(Vec::new(), Config::default());
+        let root = mb.create_tree(100);
+        let a = root.create_child(1.0, 10);
+        root.create_child(1.0, 10).finish();
+        a.add(4);
+        root.abandon();
+        assert!((root.fraction() - 0.7).abs() < 1e-9);
+        mb.listen();
+    }
+}
@@ -0,0 +1,316 @@
+//! Hiding the cursor while bars are drawn.
+//!
+//! The cursor is shown again when the `CursorGuard` is dropped, and also
+//! if the program panics while it is hidden, so the terminal is never left
+//! without a cursor. On unix, SIGINT and SIGTERM can be handled as well,
+//! see `show_cursor_on_signal`.
+
+use super::Stream;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Once;
+
+// the number of live guards, the cursor is hidden while it is not 0.
+static HIDDEN: AtomicUsize = AtomicUsize::new(0);
+static HOOK: Once = Once::new();
+// whether to handle signals while the cursor is hidden.
+static SIGNALS: AtomicBool = AtomicBool::new(false);
+// held while the first guard is created or the last one is dropped, so
+// the signal handlers are installed and restored in order.
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+/// Also show the cursor when the program gets a SIGINT or SIGTERM while a
+/// bar hides it, default is `false`.
+///
+/// The handlers are only installed while the cursor is hidden, and the
+/// previous actions are restored after. On a signal, the cursor is shown
+/// and the signal is raised again with the previous action, so it is
+/// handled as if the handler was never installed. It has no effect on
+/// other platforms than unix.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pbr::ProgressBar;
+///
+/// pbr::show_cursor_on_signal(true);
+/// let mut pb = ProgressBar::new(10);
+/// pb.set_hide_cursor(true);
+/// ```
+pub fn show_cursor_on_signal(enable: bool) {
+    SIGNALS.store(enable, Ordering::SeqCst);
+}
+
+// CursorGuard hides the cursor of a terminal while it is alive.
+pub(crate) struct CursorGuard {
+    out: Box<dyn Write + Send + Sync>,
+}
+
+impl CursorGuard {
+    // hide the cursor of the terminal on `stream`.
+    pub(crate) fn hide(stream: Stream) -> CursorGuard {
+        let out: Box<dyn Write + Send + Sync> = match stream {
+            Stream::Stdout => Box::new(io::stdout()),
+            Stream::Stderr => Box::new(io::stderr()),
+        };
+        imp::set_terminal(stream);
+        CursorGuard::on(out)
+    }
+
+    // hide the cursor by writing to `out`.
+    pub(crate) fn on(mut out: Box<dyn Write + Send + Sync>) -> CursorGuard {
+        HOOK.call_once(install_hook);
+        locked(|| {
+            if HIDDEN.fetch_add(1, Ordering::SeqCst) == 0 && SIGNALS.load(Ordering::SeqCst) {
+                imp::install_signal_handlers();
+            }
+        });
+        imp::set_visible(&mut out, false);
+        CursorGuard { out }
+    }
+}
+
+impl Drop for CursorGuard {
+    fn drop(&mut self) {
+        imp::set_visible(&mut self.out, true);
+        locked(|| {
+            if HIDDEN.fetch_sub(1, Ordering::SeqCst) == 1 {
+                imp::restore_signal_handlers();
+            }
+        });
+    }
+}
+
+fn locked<F: FnOnce()>(f: F) {
+    while BUSY
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::hint::spin_loop();
+    }
+    f();
+    BUSY.store(false, Ordering::Release);
+}
+
+fn install_hook() {
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if HIDDEN.load(Ordering::SeqCst) > 0 {
+            imp::show();
+        }
+        prev(info)
+    }));
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::Stream;
+    use crate::tty::stream_fd;
+    use libc::{sighandler_t, SIGINT, SIGTERM, SIG_IGN};
+    use std::cell::UnsafeCell;
+    use std::io::Write;
+    use std::mem::MaybeUninit;
+    use std::os::raw::c_int;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+    const SHOW: &[u8] = b"\x1B[?25h";
+    const HIDE: &[u8] = b"\x1B[?25l";
+    const SIGNALS: [c_int; 2] = [SIGINT, SIGTERM];
+
+    // the terminal of the hidden cursor, for the panic hook and the
+    // signal handler, which can not use the writer of the guard.
+    static TTY: AtomicI32 = AtomicI32::new(-1);
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    // the actions of `SIGNALS` from before ours were installed. It is
+    // only written while ours are not installed.
+    struct Saved(UnsafeCell<MaybeUninit<[libc::sigaction; 2]>>);
+    unsafe impl Sync for Saved {}
+    static SAVED: Saved = Saved(UnsafeCell::new(MaybeUninit::uninit()));
+
+    fn saved(i: usize) -> *mut libc::sigaction {
+        unsafe {
+            (*SAVED.0.get())
+                .as_mut_ptr()
+                .cast::<libc::sigaction>()
+                .add(i)
+        }
+    }
+
+    pub(super) fn set_terminal(stream: Stream) {
+        TTY.store(stream_fd(stream), Ordering::SeqCst);
+    }
+
+    pub(super) fn set_visible(out: &mut dyn Write, visible: bool) {
+        let _ = out
+            .write_all(if visible { SHOW } else { HIDE })
+            .and_then(|_| out.flush());
+    }
+
+    // show the cursor and end the half-drawn line. Only async-signal-safe
+    // functions are used.
+    pub(super) fn show() {
+        let fd = TTY.load(Ordering::SeqCst);
+        if fd >= 0 {
+            let buf = b"\x1B[?25h\n";
+            unsafe { libc::write(fd, buf.as_ptr() as *const _, buf.len()) };
+        }
+    }
+
+    pub(super) fn install_signal_handlers() {
+        for (i, &sig) in SIGNALS.iter().enumerate() {
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = on_signal as extern "C" fn(c_int) as sighandler_t;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(sig, &action, saved(i));
+                if (*saved(i)).sa_sigaction == SIG_IGN {
+                    // the signal is ignored, leave it so.
+                    libc::sigaction(sig, saved(i), ptr::null_mut());
+                }
+            }
+        }
+        INSTALLED.store(true, Ordering::SeqCst);
+    }
+
+    pub(super) fn restore_signal_handlers() {
+        if INSTALLED.swap(false, Ordering::SeqCst) {
+            for (i, &sig) in SIGNALS.iter().enumerate() {
+                unsafe { libc::sigaction(sig, saved(i), ptr::null_mut()) };
+            }
+        }
+    }
+
+    // show the cursor, then restore the previous action and raise the
+    // signal again. It is delivered once the handler returns, with the
+    // previous action and its flags, as if ours was never installed.
+    extern "C" fn on_signal(sig: c_int) {
+        if super::HIDDEN.load(Ordering::SeqCst) > 0 {
+            show();
+        }
+        let i = if sig == SIGINT { 0 } else { 1 };
+        unsafe {
+            libc::sigaction(sig, saved(i), ptr::null_mut());
+            libc::raise(sig);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::Stream;
+    use crate::tty::set_cursor_visible;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // the console of the hidden cursor, stderr if set, else stdout.
+    static STDERR: AtomicBool = AtomicBool::new(false);
+
+    fn console() -> Stream {
+        if STDERR.load(Ordering::SeqCst) {
+            Stream::Stderr
+        } else {
+            Stream::Stdout
+        }
+    }
+
+    pub(super) fn set_terminal(stream: Stream) {
+        STDERR.store(stream == Stream::Stderr, Ordering::SeqCst);
+    }
+
+    pub(super) fn set_visible(out: &mut dyn Write, visible: bool) {
+        // the console is changed right away, after what was written.
+        let _ = out.flush();
+        set_cursor_visible(console(), visible);
+    }
+
+    pub(super) fn show() {
+        set_cursor_visible(console(), true);
+    }
+
+    pub(super) fn install_signal_handlers() {}
+
+    pub(super) fn restore_signal_handlers() {}
+}
+
+#[cfg(target_os = "wasi")]
+mod imp {
+    use super::Stream;
+    use std::io::Write;
+
+    pub(super) fn set_terminal(_: Stream) {}
+
+    pub(super) fn set_visible(_: &mut dyn Write, _: bool) {}
+
+    pub(super) fn show() {}
+
+    pub(super) fn install_signal_handlers() {}
+
+    pub(super) fn restore_signal_handlers() {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CursorGuard, HIDDEN};
+    use crate::fixture::Shared;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn nested_guards() {
+        let out = Shared::default();
+        let a = CursorGuard::on(Box::new(out.clone()));
+        let b = CursorGuard::on(Box::new(out.clone()));
+        assert!(HIDDEN.load(Ordering::SeqCst) >= 2);
+        drop(a);
+        assert!(HIDDEN.load(Ordering::SeqCst) >= 1, "should stay hidden");
+        drop(b);
+        if cfg!(unix) {
+            assert_eq!(out.text(), "\x1B[?25l\x1B[?25l\x1B[?25h\x1B[?25h");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_handlers() {
+        use libc::{sighandler_t, SA_RESTART, SIGTERM};
+        use std::os::raw::c_int;
+        use std::ptr;
+
+        extern "C" fn previous(_: c_int) {}
+        let set = |action: &libc::sigaction| unsafe {
+            let mut old: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(SIGTERM, action, &mut old);
+            old
+        };
+        let get = || unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(SIGTERM, ptr::null(), &mut action);
+            action
+        };
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = previous as extern "C" fn(c_int) as sighandler_t;
+        action.sa_flags = SA_RESTART;
+        let default = set(&action);
+
+        // called directly, other tests may hold guards at the same time.
+        super::imp::install_signal_handlers();
+        assert_ne!(
+            get().sa_sigaction,
+            action.sa_sigaction,
+            "should install the handler"
+        );
+        super::imp::restore_signal_handlers();
+        let restored = get();
+        set(&default);
+        assert_eq!(
+            restored.sa_sigaction, action.sa_sigaction,
+            "should restore the action"
+        );
+        assert_eq!(
+            restored.sa_flags & SA_RESTART,
+            SA_RESTART,
+            "should keep the flags"
+        );
+    }
+}
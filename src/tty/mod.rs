@@ -1,28 +1,158 @@
 //! Most of the code in for the `terminal_size()` function taken from:
-//! https://github.com/eminence/terminal-size
+//! <https://github.com/eminence/terminal-size>
 //!
 //! A simple utility for getting the size of a terminal, and moving `n` lines up.
 //!
 //! Supports both Linux and Windows, but help is needed to test other platforms
-//!
-//!
 
-#[derive(Debug)]
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Width(pub u16);
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Height(pub u16);
 
+/// A standard stream of the process, that the handle of a bar writes to.
+///
+/// Bars on a stream that is a terminal are drawn with escape sequences,
+/// and as wide as the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+mod cursor;
+pub use self::cursor::show_cursor_on_signal;
+pub(crate) use self::cursor::CursorGuard;
+
 #[cfg(unix)]
 mod unix;
 #[cfg(unix)]
-pub use self::unix::*;
+pub(crate) use self::unix::*;
 
 #[cfg(target_os = "wasi")]
 mod wasi;
 #[cfg(target_os = "wasi")]
-pub use self::wasi::*;
+pub(crate) use self::wasi::*;
 
 #[cfg(windows)]
 mod windows;
 #[cfg(windows)]
-pub use self::windows::*;
+pub(crate) use self::windows::*;
+
+/// Returns the size of the terminal on `stream`, if it is one.
+///
+/// # Examples
+///
+/// ```
+/// use pbr::{terminal_size, Stream, Width};
+///
+/// let cols = match terminal_size(Stream::Stderr) {
+///     Some((Width(w), _)) => w,
+///     None => 80,
+/// };
+/// assert!(cols > 0);
+/// ```
+pub fn terminal_size(stream: Stream) -> Option<(Width, Height)> {
+    size_of(stream)
+}
+
+/// Returns whether `stream` is a terminal.
+///
+/// # Examples
+///
+/// ```
+/// use pbr::{is_terminal, Stream};
+///
+/// if !is_terminal(Stream::Stdout) {
+///     println!("stdout is redirected");
+/// }
+/// ```
+pub fn is_terminal(stream: Stream) -> bool {
+    is_tty(stream)
+}
+
+// how long a size is kept before it is read again, so a resized terminal
+// is picked up within it.
+const SIZE_TTL: Duration = Duration::from_secs(1);
+
+// TermSize caches the size of the terminal on a stream, so drawing a bar
+// does not ask the terminal every time. The size is read again after
+// `SIZE_TTL`. Without a stream, the size is the one of the terminal on
+// stdout, or else on stderr.
+pub(crate) struct TermSize {
+    stream: Option<Stream>,
+    size: Option<(Width, Height)>,
+    // when the size was read.
+    read: Option<Instant>,
+}
+
+impl TermSize {
+    pub(crate) fn new(stream: Option<Stream>) -> TermSize {
+        TermSize {
+            stream,
+            size: None,
+            read: None,
+        }
+    }
+
+    pub(crate) fn get(&mut self) -> Option<(Width, Height)> {
+        let now = Instant::now();
+        let fresh = match self.read {
+            Some(at) => now - at < SIZE_TTL,
+            None => false,
+        };
+        if !fresh {
+            self.size = match self.stream {
+                Some(stream) => size_of(stream),
+                None => size_of(Stream::Stdout).or_else(|| size_of(Stream::Stderr)),
+            };
+            self.read = Some(now);
+        }
+        self.size
+    }
+}
+
+// the state of the progress reported to the terminal with `OSC 9;4`,
+// shown in the tab or the taskbar by terminals that support it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Taskbar {
+    // remove the progress.
+    Clear,
+    // progress in percent.
+    Normal(u8),
+    // progress in percent, in the error color.
+    Error(u8),
+    // progress of an unknown amount of work.
+    Indeterminate,
+    // progress in percent, in the warning color.
+    Warning(u8),
+}
+
+// the `OSC 9;4` sequence that reports `state` to the terminal.
+pub(crate) fn taskbar(state: Taskbar) -> String {
+    let (st, pr) = match state {
+        Taskbar::Clear => (0, 0),
+        Taskbar::Normal(pr) => (1, pr),
+        Taskbar::Error(pr) => (2, pr),
+        Taskbar::Indeterminate => (3, 0),
+        Taskbar::Warning(pr) => (4, pr),
+    };
+    format!("\x1B]9;4;{};{}\x1B\\", st, pr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{size_of, Stream, TermSize};
+
+    #[test]
+    fn term_size_without_stream() {
+        let probed = size_of(Stream::Stdout).or_else(|| size_of(Stream::Stderr));
+        assert_eq!(
+            TermSize::new(None).get(),
+            probed,
+            "should probe the streams"
+        );
+    }
+}
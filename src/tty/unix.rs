@@ -1,4 +1,5 @@
-use super::{Height, Width};
+use super::{Height, Stream, Width};
+use std::os::unix::io::RawFd;
 
 // We need to convert from c_int to c_ulong at least on DragonFly and FreeBSD.
 #[cfg(any(target_os = "dragonfly", target_os = "freebsd"))]
@@ -12,16 +13,29 @@ fn ioctl_conv<T: Copy>(v: T) -> T {
     v
 }
 
-/// Returns the size of the terminal, if available.
+// the file descriptor of `stream`.
+pub(crate) fn stream_fd(stream: Stream) -> RawFd {
+    match stream {
+        Stream::Stdout => libc::STDOUT_FILENO,
+        Stream::Stderr => libc::STDERR_FILENO,
+    }
+}
+
+/// Returns whether `stream` is a terminal.
+pub(crate) fn is_tty(stream: Stream) -> bool {
+    unsafe { libc::isatty(stream_fd(stream)) == 1 }
+}
+
+/// Returns the size of the terminal on `stream`, if available.
 ///
-/// If STDOUT is not a tty, returns `None`
-pub fn terminal_size() -> Option<(Width, Height)> {
-    use libc::{ioctl, isatty, winsize, STDOUT_FILENO, TIOCGWINSZ};
-    let is_tty: bool = unsafe { isatty(STDOUT_FILENO) == 1 };
+/// If `stream` is not a tty, returns `None`
+pub(crate) fn size_of(stream: Stream) -> Option<(Width, Height)> {
+    use libc::{ioctl, winsize, TIOCGWINSZ};
 
-    if !is_tty {
+    if !is_tty(stream) {
         return None;
     }
+    let fd = stream_fd(stream);
 
     let (rows, cols) = unsafe {
         let mut winsize = winsize {
@@ -30,7 +44,7 @@ pub fn terminal_size() -> Option<(Width, Height)> {
             ws_xpixel: 0,
             ws_ypixel: 0,
         };
-        ioctl(STDOUT_FILENO, ioctl_conv(TIOCGWINSZ), &mut winsize);
+        ioctl(fd, ioctl_conv(TIOCGWINSZ), &mut winsize);
         let rows = if winsize.ws_row > 0 {
             winsize.ws_row
         } else {
@@ -41,7 +55,7 @@ pub fn terminal_size() -> Option<(Width, Height)> {
         } else {
             0
         };
-        (rows as u16, cols as u16)
+        (rows, cols)
     };
 
     if rows > 0 && cols > 0 {
@@ -52,10 +66,15 @@ pub fn terminal_size() -> Option<(Width, Height)> {
 }
 
 /// Return string that move the cursor `n` lines up.
-pub fn move_cursor_up(n: usize) -> String {
+pub(crate) fn move_cursor_up(_stream: Stream, n: usize) -> String {
     format!("\x1B[{}A", n)
 }
 
+/// Return string that clear the line of the cursor.
+pub(crate) fn clear_line(_stream: Stream) -> String {
+    "\x1B[2K".to_string()
+}
+
 #[cfg(not(target_os = "redox"))]
 #[test]
 /// Compare with the output of `stty size`
@@ -76,12 +95,12 @@ fn compare_with_stty() {
 
     // stdout is "rows cols"
     let mut data = stdout.split_whitespace();
-    let rows = u16::from_str_radix(data.next().unwrap(), 10).unwrap();
-    let cols = u16::from_str_radix(data.next().unwrap(), 10).unwrap();
+    let rows = data.next().unwrap().parse::<u16>().unwrap();
+    let cols = data.next().unwrap().parse::<u16>().unwrap();
     println!("{}", stdout);
     println!("{} {}", rows, cols);
 
-    if let Some((Width(w), Height(h))) = terminal_size() {
+    if let Some((Width(w), Height(h))) = size_of(Stream::Stderr) {
         assert_eq!(rows, h);
         assert_eq!(cols, w);
     }
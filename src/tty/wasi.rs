@@ -1,15 +1,25 @@
-use super::{Height, Width};
+use super::{Height, Stream, Width};
+
+/// For WASI so far it will return false
+pub(crate) fn is_tty(_stream: Stream) -> bool {
+    false
+}
 
 /// For WASI so far it will return none
 ///
 /// For background https://github.com/WebAssembly/WASI/issues/42
-pub fn terminal_size() -> Option<(Width, Height)> {
-    return None;
+pub(crate) fn size_of(_stream: Stream) -> Option<(Width, Height)> {
+    None
 }
 
 /// This is inherited from unix and will work only when wasi executed on unix.
 ///
 /// For background https://github.com/WebAssembly/WASI/issues/42
-pub fn move_cursor_up(n: usize) -> String {
+pub(crate) fn move_cursor_up(_stream: Stream, n: usize) -> String {
     format!("\x1B[{}A", n)
 }
+
+/// This is inherited from unix and will work only when wasi executed on unix.
+pub(crate) fn clear_line(_stream: Stream) -> String {
+    "\x1B[2K".to_string()
+}
@@ -1,11 +1,30 @@
-use super::{Height, Width};
+use super::{Height, Stream, Width};
+use winapi::shared::ntdef::HANDLE;
 
-/// Returns the size of the terminal, if available.
+// the standard handle of `stream`.
+fn handle(stream: Stream) -> HANDLE {
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+    let std = match stream {
+        Stream::Stdout => STD_OUTPUT_HANDLE,
+        Stream::Stderr => STD_ERROR_HANDLE,
+    };
+    unsafe { GetStdHandle(std) }
+}
+
+/// Returns whether `stream` is a console.
+pub(crate) fn is_tty(stream: Stream) -> bool {
+    use winapi::um::consoleapi::GetConsoleMode;
+    let mut mode = 0;
+    unsafe { GetConsoleMode(handle(stream), &mut mode) != 0 }
+}
+
+/// Returns the size of the console on `stream`, if available.
 ///
 /// Note that this returns the size of the actual command window, and
 /// not the overall size of the command window buffer
-pub fn terminal_size() -> Option<(Width, Height)> {
-    if let Some((_, csbi)) = get_csbi() {
+pub(crate) fn size_of(stream: Stream) -> Option<(Width, Height)> {
+    if let Some((_, csbi)) = get_csbi_of(handle(stream)) {
         let w: Width = Width((csbi.srWindow.Right - csbi.srWindow.Left) as u16);
         let h: Height = Height((csbi.srWindow.Bottom - csbi.srWindow.Top) as u16);
         Some((w, h))
@@ -14,11 +33,11 @@ pub fn terminal_size() -> Option<(Width, Height)> {
     }
 }
 
-/// move the cursor `n` lines up; return an empty string, just to
-/// be aligned with the unix version.
-pub fn move_cursor_up(n: usize) -> String {
+/// move the cursor of the console on `stream` `n` lines up; return an
+/// empty string, just to be aligned with the unix version.
+pub(crate) fn move_cursor_up(stream: Stream, n: usize) -> String {
     use winapi::um::wincon::{SetConsoleCursorPosition, COORD};
-    if let Some((hand, csbi)) = get_csbi() {
+    if let Some((hand, csbi)) = get_csbi_of(handle(stream)) {
         unsafe {
             SetConsoleCursorPosition(
                 hand,
@@ -32,19 +51,51 @@ pub fn move_cursor_up(n: usize) -> String {
     "".to_string()
 }
 
-fn get_csbi() -> Option<(
-    winapi::shared::ntdef::HANDLE,
-    winapi::um::wincon::CONSOLE_SCREEN_BUFFER_INFO,
-)> {
-    use winapi::shared::ntdef::HANDLE;
-    use winapi::um::processenv::GetStdHandle;
-    use winapi::um::winbase::STD_OUTPUT_HANDLE;
+/// clear the line of the cursor of the console on `stream` right away, so
+/// whatever is to be written before must be written first; return an
+/// empty string, just to be aligned with the unix version.
+pub(crate) fn clear_line(stream: Stream) -> String {
+    use winapi::um::wincon::{FillConsoleOutputCharacterA, COORD};
+    if let Some((hand, csbi)) = get_csbi_of(handle(stream)) {
+        let mut written = 0;
+        unsafe {
+            FillConsoleOutputCharacterA(
+                hand,
+                b' ' as i8,
+                csbi.dwSize.X as u32,
+                COORD {
+                    X: 0,
+                    Y: csbi.dwCursorPosition.Y,
+                },
+                &mut written,
+            );
+        }
+    }
+    "".to_string()
+}
+
+/// Show or hide the cursor of the console on `stream`.
+pub(crate) fn set_cursor_visible(stream: Stream, visible: bool) {
+    use winapi::um::wincon::{GetConsoleCursorInfo, SetConsoleCursorInfo, CONSOLE_CURSOR_INFO};
+    if let Some((hand, _)) = get_csbi_of(handle(stream)) {
+        let mut info = CONSOLE_CURSOR_INFO {
+            dwSize: 0,
+            bVisible: 0,
+        };
+        unsafe {
+            if GetConsoleCursorInfo(hand, &mut info) != 0 {
+                info.bVisible = visible as i32;
+                SetConsoleCursorInfo(hand, &info);
+            }
+        }
+    }
+}
+
+fn get_csbi_of(hand: HANDLE) -> Option<(HANDLE, winapi::um::wincon::CONSOLE_SCREEN_BUFFER_INFO)> {
     use winapi::um::wincon::{
         GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO, COORD, SMALL_RECT,
     };
 
-    let hand: HANDLE = unsafe { GetStdHandle(STD_OUTPUT_HANDLE) };
-
     let zc = COORD { X: 0, Y: 0 };
     let mut csbi = CONSOLE_SCREEN_BUFFER_INFO {
         dwSize: zc.clone(),
@@ -0,0 +1,112 @@
+use crate::ProgressBar;
+use std::io::{self, Write};
+
+/// ProgressWriter wraps a writer and adds the bytes written to it to a
+/// `ProgressBar`, see `ProgressBar::wrap_write`.
+///
+/// Only the bytes accepted by the underlying writer are counted.
+pub struct ProgressWriter<'a, W, T: Write> {
+    inner: W,
+    pb: &'a mut ProgressBar<T>,
+}
+
+impl<T: Write> ProgressBar<T> {
+    /// Wrap `writer` so writing to it updates the bar.
+    ///
+    /// Unlike writing to the `ProgressBar` itself, the bytes are
+    /// forwarded to `writer`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io;
+    /// use pbr::{ProgressBar, Units};
+    ///
+    /// let mut file = File::open("/usr/share/dict/words").unwrap();
+    /// let n_bytes = file.metadata().unwrap().len();
+    /// let out = File::create("copy-words").unwrap();
+    ///
+    /// let mut pb = ProgressBar::new(n_bytes);
+    /// pb.set_units(Units::Bytes);
+    /// io::copy(&mut file, &mut pb.wrap_write(out)).unwrap();
+    /// pb.finish_print("done");
+    /// ```
+    pub fn wrap_write<W>(&mut self, writer: W) -> ProgressWriter<'_, W, T> {
+        ProgressWriter {
+            inner: writer,
+            pb: self,
+        }
+    }
+}
+
+impl<'a, W, T: Write> ProgressWriter<'a, W, T> {
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Writing directly to the underlying writer does not update the bar.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<'a, W: Write, T: Write> Write for ProgressWriter<'a, W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.pb.add(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::ProgressBar;
+    use std::io::{self, Cursor, Write};
+
+    // Short accepts at most 3 bytes in every write.
+    struct Short(Vec<u8>);
+
+    impl Write for Short {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copy() {
+        let data = vec![7u8; 1000];
+        let mut pb = ProgressBar::with_config(Vec::new(), 1000, Config::default());
+        let mut w = pb.wrap_write(Vec::new());
+        io::copy(&mut Cursor::new(&data), &mut w).unwrap();
+        assert_eq!(w.into_inner(), data);
+        assert_eq!(pb.current, 1000, "should count the bytes written");
+    }
+
+    #[test]
+    fn short_writes() {
+        let mut pb = ProgressBar::with_config(Vec::new(), 10, Config::default());
+        let mut w = pb.wrap_write(Short(Vec::new()));
+        assert_eq!(w.write(b"abcdef").unwrap(), 3);
+        assert_eq!(pb.current, 3, "should count only the accepted bytes");
+    }
+}
@@ -1,6 +1,6 @@
 extern crate pbr;
 
-use pbr::{PbIter, ProgressBar};
+use pbr::{PbIter, ProgressBar, ProgressIterator};
 use std::thread;
 use std::time::Duration;
 
@@ -41,7 +41,7 @@ fn timeout_example() {
     let count = 10;
     let mut pb = ProgressBar::new(count * 20);
     pb.tick_format("▏▎▍▌▋▊▉██▉▊▋▌▍▎▏");
-    pb.show_message = true;
+    pb.set_show_tick(true);
     pb.inc();
     for _ in 0..count {
         for _ in 0..20 {
@@ -69,6 +69,7 @@ fn tick_before_start() {
     let count = 100;
     let mut pb = ProgressBar::new(count);
     pb.tick_format("▏▎▍▌▋▊▉██▉▊▋▌▍▎▏");
+    pb.set_show_tick(true);
     pb.tick();
     for _ in 0..count {
         pb.tick();
@@ -86,11 +87,11 @@ fn npm_bar() {
     let mut pb = ProgressBar::new(count * 5);
     pb.tick_format("\\|/-");
     pb.format("|#--|");
-    pb.show_tick = true;
-    pb.show_speed = false;
-    pb.show_percent = false;
-    pb.show_counter = false;
-    pb.show_time_left = false;
+    pb.set_show_tick(true);
+    pb.set_show_speed(false);
+    pb.set_show_percent(false);
+    pb.set_show_counter(false);
+    pb.set_show_time_left(false);
     pb.inc();
     for _ in 0..count {
         for _ in 0..5 {
@@ -120,3 +121,24 @@ fn final_redraw_max_refresh_rate() {
     }
     pb.finish_println("done!");
 }
+
+#[test]
+fn progress_iter() {
+    let pb = ProgressBar::on(Vec::new(), 0);
+    let mut it = vec![1, 2, 3].into_iter().progress_with(pb);
+    assert_eq!(it.progress_bar().total(), 3, "should use the iterator size");
+    assert_eq!(it.len(), 3);
+    assert_eq!(it.next_back(), Some(3));
+    assert_eq!(it.by_ref().sum::<i32>(), 3);
+    assert!(
+        it.progress_bar().is_finish(),
+        "should finish when exhausted"
+    );
+}
+
+#[test]
+fn progress_iter_upper_bound() {
+    let pb = ProgressBar::on(Vec::new(), 0);
+    let mut it = (0..10).filter(|i| i % 2 == 0).progress_with(pb);
+    assert_eq!(it.progress_bar().total(), 10, "should use the upper bound");
+}